@@ -1,6 +1,30 @@
 use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
 
+mod msgpack;
+mod prime_count;
+
+// Exit codes: 0 on success, EXIT_FAILURE when a computation or I/O step
+// fails (or a check such as verify-primes finds a non-prime), EXIT_USAGE for
+// bad arguments or an unknown command, and EXIT_INTERRUPTED after Ctrl-C.
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_INTERRUPTED: i32 = 130;
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(0);
+
+// Diagnostics go to stderr so stdout stays clean for piping.
+macro_rules! verbose {
+    ($level:expr, $($arg:tt)*) => {
+        if VERBOSITY.load(Ordering::Relaxed) >= $level {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
 fn arctan_inv(x: i64, scale: num_bigint::BigInt) -> num_bigint::BigInt {
     use num_bigint::BigInt;
     use num_traits::{One, Zero};
@@ -13,7 +37,7 @@ fn arctan_inv(x: i64, scale: num_bigint::BigInt) -> num_bigint::BigInt {
     let mut sign: i32 = -1;
 
     loop {
-        term = term / &x2;
+        term /= &x2;
         let denom = (&k * 2u32) + 1u32;
         let add = &term / denom;
         if add.is_zero() {
@@ -27,6 +51,7 @@ fn arctan_inv(x: i64, scale: num_bigint::BigInt) -> num_bigint::BigInt {
         sign = -sign;
         k += 1u32;
     }
+    verbose!(1, "arctan(1/{x}): {k} terms");
     sum
 }
 
@@ -40,13 +65,530 @@ fn pow10(n: u32) -> num_bigint::BigInt {
     v
 }
 
+fn isqrt(n: &num_bigint::BigInt) -> num_bigint::BigInt {
+    use num_bigint::BigInt;
+    use num_traits::{Signed, Zero};
+
+    assert!(!n.is_negative(), "isqrt of a negative number");
+    if n.is_zero() {
+        return BigInt::zero();
+    }
+    // 2^ceil(bits/2) is always >= sqrt(n), so Newton descends monotonically.
+    let mut x = BigInt::from(1u32) << n.bits().div_ceil(2);
+    loop {
+        let y = (&x + n / &x) >> 1u32;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+fn isqrt_rem(n: &num_bigint::BigInt) -> (num_bigint::BigInt, num_bigint::BigInt) {
+    let root = isqrt(n);
+    let rem = n - &root * &root;
+    (root, rem)
+}
+
 fn compute_pi(digits: u32) -> String {
     let extra: u32 = 5;
+    verbose!(2, "machin: working precision {} digits", digits + extra);
     let scale = pow10(digits + extra);
     let atan5 = arctan_inv(5, scale.clone());
     let atan239 = arctan_inv(239, scale.clone());
     let pi_scaled = (atan5 * 16u32) - (atan239 * 4u32);
+    format_scaled_pi(pi_scaled, digits, extra)
+}
+
+fn compute_e(digits: u32) -> String {
+    use num_traits::Zero;
+
+    // e = sum of 1/k!. Each of the roughly digits / log10(digits) terms is
+    // truncated by under one unit, which the guard digits absorb.
+    let extra: u32 = 10;
+    let scale = pow10(digits + extra);
+    let mut term = scale.clone();
+    let mut sum = scale;
+    let mut k = 1u32;
+    while !term.is_zero() {
+        term /= k;
+        sum += &term;
+        k += 1;
+    }
+    verbose!(2, "e: {k} terms at {} digits", digits + extra);
+    format_scaled_pi(sum, digits, extra)
+}
+
+// Continued fraction terms of e taken from compute_e. The printed value is
+// within half a unit of its last place, so the terms are extracted from both
+// ends of that interval and only the ones they agree on are kept (less the
+// last, which an endpoint may cut short); precision doubles until `count`
+// terms survive.
+fn e_continued_fraction(count: usize) -> Vec<num_bigint::BigInt> {
+    let mut digits = (2 * count as u32).saturating_add(20);
+    loop {
+        let (num, den) = decimal_to_fraction(&compute_e(digits)).expect("e digits parse");
+        let (num, den) = (num * 2u32, den * 2u32);
+        let low = continued_fraction(&(&num - 1u32), &den, count + 1);
+        let high = continued_fraction(&(&num + 1u32), &den, count + 1);
+        let agreed = low.iter().zip(&high).take_while(|(a, b)| a == b).count();
+        if agreed > count {
+            let mut terms = low;
+            terms.truncate(count);
+            return terms;
+        }
+        verbose!(1, "e --cf: {digits} digits give {agreed} terms, doubling");
+        digits = digits.saturating_mul(2);
+    }
+}
+
+// The first `count` fractional digits of Champernowne's constant in `base`:
+// 1, 2, 3, ... written out in that base and strung together.
+fn champernowne_digits(count: usize, base: u32) -> String {
+    let mut out = String::with_capacity(count);
+    let mut buf = Vec::new();
+    let mut n = 1u64;
+    while out.len() < count {
+        let mut m = n;
+        while m > 0 {
+            buf.push(char::from_digit((m % base as u64) as u32, base).unwrap_or('?'));
+            m /= base as u64;
+        }
+        out.extend(buf.drain(..).rev());
+        n += 1;
+    }
+    out.truncate(count);
+    out
+}
+
+fn compute_pi_agm(digits: u32) -> String {
+    use num_bigint::BigInt;
+
+    // Each iteration roughly doubles the correct digits but also loses a
+    // little precision to the repeated square roots, hence the wider guard.
+    let extra: u32 = 10;
+    verbose!(2, "agm: working precision {} digits", digits + extra);
+    let scale = pow10(digits + extra);
+    let mut a = scale.clone();
+    let mut b = isqrt(&(&scale * &scale / 2u32));
+    let mut t = &scale / 4u32;
+    let mut p = BigInt::from(1u32);
+    let mut iterations = 0;
+
+    loop {
+        iterations += 1;
+        let next_a = (&a + &b) / 2u32;
+        b = isqrt(&(&a * &b));
+        let diff = &a - &next_a;
+        t -= &p * &diff * &diff / &scale;
+        p *= 2u32;
+        a = next_a;
+        if (&a - &b).magnitude().bits() <= 1 {
+            break;
+        }
+    }
+
+    verbose!(1, "agm: {iterations} iterations");
+    let sum = &a + &b;
+    let pi_scaled = &sum * &sum / (t * 4u32);
+    format_scaled_pi(pi_scaled, digits, extra)
+}
+
+// Binary splitting over terms [a, b) of the Chudnovsky series, returning
+// (P, Q, T) so that merging two halves needs only a few big products.
+fn chudnovsky_split(
+    a: u64,
+    b: u64,
+) -> (num_bigint::BigInt, num_bigint::BigInt, num_bigint::BigInt) {
+    use num_bigint::BigInt;
+
+    if b - a == 1 {
+        let (p, q) = if a == 0 {
+            (BigInt::from(1u32), BigInt::from(1u32))
+        } else {
+            let p = BigInt::from(6 * a - 5) * (2 * a - 1) * (6 * a - 1);
+            // 640320^3 / 24
+            let q = BigInt::from(a).pow(3) * 10_939_058_860_032_000u64;
+            (p, q)
+        };
+        let mut t = &p * (BigInt::from(545_140_134u64) * a + 13_591_409u64);
+        if a % 2 == 1 {
+            t = -t;
+        }
+        return (p, q, t);
+    }
+    let mid = a + (b - a) / 2;
+    let (p1, q1, t1) = chudnovsky_split(a, mid);
+    let (p2, q2, t2) = chudnovsky_split(mid, b);
+    (&p1 * &p2, &q1 * &q2, t1 * &q2 + p1 * t2)
+}
+
+// The --low-memory form of chudnovsky_split. P is only built where a parent
+// merge needs it, which skips it along the whole right spine (including the
+// root, whose P is full size and unused), and each merge consumes its inputs
+// as it goes instead of holding all five products at once.
+fn chudnovsky_split_lean(
+    a: u64,
+    b: u64,
+    need_p: bool,
+) -> (
+    Option<num_bigint::BigInt>,
+    num_bigint::BigInt,
+    num_bigint::BigInt,
+) {
+    if b - a == 1 {
+        let (p, q, t) = chudnovsky_split(a, b);
+        return (need_p.then_some(p), q, t);
+    }
+    let mid = a + (b - a) / 2;
+    let (p1, q1, t1) = chudnovsky_split_lean(a, mid, true);
+    let (p2, q2, t2) = chudnovsky_split_lean(mid, b, need_p);
+    let p1 = p1.expect("left halves always carry P");
+    let p = p2.map(|p2| p2 * &p1);
+    let mut t = t1 * &q2;
+    t += p1 * t2;
+    (p, q1 * q2, t)
+}
+
+fn compute_pi_chudnovsky_lean(digits: u32) -> String {
+    let extra: u32 = 5;
+    verbose!(
+        2,
+        "chudnovsky: working precision {} digits, low memory",
+        digits + extra
+    );
+    let terms = (digits + extra) as u64 / 14 + 2;
+    verbose!(1, "chudnovsky: {terms} terms");
+    let (_, mut q, mut t) = chudnovsky_split_lean(0, terms, false);
+    // Q and T carry about twice the bits the answer needs; only their ratio
+    // matters, so both are cut down to the working precision plus 128 guard
+    // bits before the full-width square root and division.
+    let keep = ((digits + extra) as f64 * std::f64::consts::LOG2_10) as u64 + 128;
+    let shift = q.bits().min(t.bits()).saturating_sub(keep) as usize;
+    q >>= shift;
+    t >>= shift;
+    let scale = pow10(digits + extra);
+    q *= 426_880u32;
+    q *= isqrt(&(&scale * &scale * 10005u32));
+    let x = q / t;
+    format_scaled_pi(x, digits, extra)
+}
+
+fn compute_pi_chudnovsky(digits: u32) -> String {
+    let extra: u32 = 5;
+    verbose!(2, "chudnovsky: working precision {} digits", digits + extra);
+    // Each term adds about 14.18 digits.
+    let terms = (digits + extra) as u64 / 14 + 2;
+    verbose!(1, "chudnovsky: {terms} terms");
+    let (_, q, t) = chudnovsky_split(0, terms);
+    let scale = pow10(digits + extra);
+    let sqrt_10005 = isqrt(&(&scale * &scale * 10005u32));
+    let pi_scaled = q * 426_880u32 * sqrt_10005 / t;
+    format_scaled_pi(pi_scaled, digits, extra)
+}
+
+// The spigot is quadratic in the digit count, so past this it is far slower
+// than the other BigInt engines.
+const SPIGOT_MAX_DIGITS: u32 = 50_000;
+
+fn pi_spigot(digits: u32) -> String {
+    // One digit past the request decides the rounding, so the output
+    // matches the rounded BigInt engines rather than a plain truncation.
+    let keep = digits as usize + 1;
+    let mut kept: Vec<u8> = pi_digits().take(keep + 1).collect();
+    if kept.pop().is_some_and(|next| next >= 5) {
+        for d in kept.iter_mut().rev() {
+            if *d == 9 {
+                *d = 0;
+            } else {
+                *d += 1;
+                break;
+            }
+        }
+    }
+    let text: String = kept.iter().map(|d| char::from(b'0' + d)).collect();
+    let (head, tail) = text.split_at(1);
+    format!("{head}.{tail}")
+}
+
+// Gibbons' unbounded spigot: the decimal digits of pi (3, 1, 4, 1, 5, ...),
+// truncated rather than rounded, without a preset total. The state is a
+// linear fractional transformation with BigInt coefficients that grow with
+// the digits produced so far, so stopping after n digits costs only the work
+// for those n.
+struct PiDigits {
+    num: num_bigint::BigInt,
+    acc: num_bigint::BigInt,
+    den: num_bigint::BigInt,
+    k: u32,
+}
+
+fn pi_digits() -> PiDigits {
+    PiDigits {
+        num: 1.into(),
+        acc: 0.into(),
+        den: 1.into(),
+        k: 0,
+    }
+}
+
+impl PiDigits {
+    // floor((num * n + acc) / den), the value's integer part at x = n.
+    fn extract(&self, n: u32) -> num_bigint::BigInt {
+        (&self.num * n + &self.acc) / &self.den
+    }
+}
+
+impl Iterator for PiDigits {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        use num_traits::ToPrimitive;
+
+        loop {
+            // Compose the next term of the series in place.
+            self.k += 1;
+            let k2 = 2 * self.k + 1;
+            self.acc += &self.num * 2u32;
+            self.acc *= k2;
+            self.den *= k2;
+            self.num *= self.k;
+            if self.num > self.acc {
+                continue;
+            }
+            // The digit is settled once x = 3 and x = 4 agree on it.
+            let digit = self.extract(3);
+            if digit != self.extract(4) {
+                continue;
+            }
+            self.acc -= &self.den * &digit;
+            self.acc *= 10u32;
+            self.num *= 10u32;
+            return Some(digit.to_u8().expect("a decimal digit"));
+        }
+    }
+}
+
+// Writes up to `digits` digits (unbounded when None), stopping right after
+// the first occurrence of `until`. Returns whether `until` was seen.
+fn stream_pi(
+    digits: Option<usize>,
+    until: Option<&[u8]>,
+    flush_bytes: usize,
+) -> std::io::Result<bool> {
+    use std::io::Write;
+
+    let mut out = std::io::BufWriter::with_capacity(flush_bytes, std::io::stdout().lock());
+    let mut unflushed = 0;
+    let mut window: std::collections::VecDeque<u8> = std::collections::VecDeque::new();
+    let mut found = false;
+    let limit = digits.map_or(usize::MAX, |d| d + 1);
+    for (i, digit) in pi_digits().take(limit).enumerate() {
+        out.write_all(&[b'0' + digit])?;
+        if i == 0 {
+            out.write_all(b".")?;
+        }
+        unflushed += 1;
+        if unflushed >= flush_bytes {
+            out.flush()?;
+            unflushed = 0;
+        }
+        if let Some(pattern) = until {
+            // Decimal places only; the leading 3 is not part of a match.
+            if i > 0 {
+                window.push_back(digit);
+                if window.len() > pattern.len() {
+                    window.pop_front();
+                }
+                if window.iter().eq(pattern.iter()) {
+                    found = true;
+                    break;
+                }
+            }
+        }
+    }
+    out.write_all(b"\n")?;
+    out.flush()?;
+    Ok(found)
+}
+
+type PiEngine = fn(u32) -> String;
+
+// Every pi engine by --method name, with the largest digit count it accepts
+// and its suite name in `bench-pi --all-methods`.
+const PI_METHODS: [(&str, PiEngine, u32, &str); 4] = [
+    ("machin", compute_pi, u32::MAX, "pi_machin"),
+    ("agm", compute_pi_agm, u32::MAX, "pi_agm"),
+    (
+        "chudnovsky",
+        compute_pi_chudnovsky,
+        u32::MAX,
+        "pi_chudnovsky",
+    ),
+    ("spigot", pi_spigot, SPIGOT_MAX_DIGITS, "pi_spigot"),
+];
 
+fn pi_method_or_exit(method: &str) -> (PiEngine, u32) {
+    match PI_METHODS.iter().find(|(name, ..)| *name == method) {
+        Some(&(_, engine, max_digits, _)) => (engine, max_digits),
+        None => {
+            eprintln!("unknown pi method: {method} (expected machin, agm, chudnovsky or spigot)");
+            std::process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+// Runs every engine at the same precision and prints a timing table; returns
+// false if any of them disagrees with the first.
+fn compare_pi_methods(digits: u32) -> bool {
+    let mut rows = vec![vec![
+        "Method".to_string(),
+        "Time".to_string(),
+        "Result".to_string(),
+    ]];
+    let mut reference: Option<(&str, String)> = None;
+    let mut agree = true;
+    for &(name, engine, max_digits, _) in &PI_METHODS {
+        if digits > max_digits {
+            rows.push(vec![
+                name.to_string(),
+                "-".to_string(),
+                format!("skipped (limited to {max_digits} digits)"),
+            ]);
+            continue;
+        }
+        let start = Instant::now();
+        let pi = engine(digits);
+        let time = format!("{:.3}s", start.elapsed().as_secs_f64());
+        let result = match &reference {
+            None => "reference".to_string(),
+            Some((_, expected)) if *expected == pi => "match".to_string(),
+            Some((first, expected)) => {
+                agree = false;
+                format!(
+                    "MISMATCH with {first} at digit {}",
+                    first_pi_mismatch(expected, &pi)
+                )
+            }
+        };
+        rows.push(vec![name.to_string(), time, result]);
+        reference.get_or_insert((name, pi));
+    }
+    println!("{}", render_table(&rows));
+    if agree {
+        println!("All methods agree on {digits} digits");
+    }
+    agree
+}
+
+// Both strings start "3.", so the byte index past the point is the digit
+// position.
+fn first_pi_mismatch(expected: &str, actual: &str) -> usize {
+    let at = expected
+        .bytes()
+        .zip(actual.bytes())
+        .position(|(a, b)| a != b)
+        .unwrap_or(expected.len().min(actual.len()));
+    at.saturating_sub(1)
+}
+
+// The engines `bench-pi --all-methods` can time at this precision, after
+// computing each once and checking it against the first; exits on a
+// mismatch, since timing a wrong answer is meaningless.
+fn spot_checked_pi_methods(digits: u32) -> Vec<(&'static str, PiEngine, &'static str)> {
+    let mut reference: Option<(&str, String)> = None;
+    let mut methods = Vec::new();
+    for &(name, engine, max_digits, suite) in &PI_METHODS {
+        if digits > max_digits {
+            continue;
+        }
+        let pi = engine(digits);
+        if let Some((first, expected)) = &reference {
+            if *expected != pi {
+                eprintln!(
+                    "{name} disagrees with {first} at digit {}; not benchmarking",
+                    first_pi_mismatch(expected, &pi)
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+        }
+        reference.get_or_insert((name, pi));
+        methods.push((name, engine, suite));
+    }
+    methods
+}
+
+fn pi_methods_table(results: &[BenchResult], digits: u32) -> String {
+    let fastest = results.iter().map(|r| r.overall).fold(0.0, f64::max);
+    let mut rows = vec![vec![
+        "Method".to_string(),
+        "Digits/sec".to_string(),
+        "Computations/sec".to_string(),
+        "Vs fastest".to_string(),
+    ]];
+    for &(name, _, max_digits, suite) in &PI_METHODS {
+        match results.iter().find(|r| r.name == suite) {
+            Some(r) => rows.push(vec![
+                name.to_string(),
+                format!("{:.0}", digits as f64 * r.overall),
+                format!("{:.2}", r.overall),
+                format!("{:.1}%", 100.0 * r.overall / fastest),
+            ]),
+            None if digits > max_digits => rows.push(vec![
+                name.to_string(),
+                "-".to_string(),
+                "-".to_string(),
+                format!("skipped (limited to {max_digits} digits)"),
+            ]),
+            None => {}
+        }
+    }
+    format!("{}\n", render_table(&rows))
+}
+
+fn pi_within_budget(engine: PiEngine, budget: Duration, max_digits: u32) -> (u32, String) {
+    let start = Instant::now();
+    let mut digits = 100.min(max_digits);
+    let mut best = (digits, engine(digits));
+    let mut last = start.elapsed();
+    // Only whole runs are kept, so a run that would overshoot the budget is
+    // never started. Cost is modelled as digits^exponent, with the exponent
+    // taken from the last doubling (quadratic until two timings exist); the
+    // final run is shrunk to what the remaining time should afford.
+    let mut exponent = 2.0f64;
+    while digits < max_digits {
+        let remaining = budget.saturating_sub(start.elapsed()).as_secs_f64() * 0.9;
+        let affordable = (remaining / last.as_secs_f64().max(1e-6)).powf(1.0 / exponent);
+        let factor = affordable.min(2.0);
+        if factor < 1.05 {
+            break;
+        }
+        let next = ((digits as f64 * factor) as u32).min(max_digits);
+        let run = Instant::now();
+        let pi = engine(next);
+        let took = run.elapsed();
+        verbose!(1, "pi budget: {next} digits in {:.3}s", took.as_secs_f64());
+        if last > Duration::from_millis(1) {
+            let ratio = took.as_secs_f64() / last.as_secs_f64();
+            exponent = (ratio.ln() / factor.ln()).max(1.0);
+        }
+        digits = next;
+        best = (digits, pi);
+        last = took;
+    }
+    best
+}
+
+fn pi_find(pattern: &str, digits: u32) -> Option<usize> {
+    // The last few digits can still move with rounding, so compute past the
+    // search range and only look within the settled part.
+    let pi = compute_pi(digits + 10);
+    let fraction = &pi[2..2 + digits as usize];
+    // str::find uses the two-way algorithm, linear in the searched digits.
+    fraction.find(pattern).map(|index| index + 1)
+}
+
+fn format_scaled_pi(pi_scaled: num_bigint::BigInt, digits: u32, extra: u32) -> String {
     let rounding = 5u32 * pow10(extra - 1);
     let pi_rounded = (pi_scaled + rounding) / pow10(extra);
     let mut pi_str = pi_rounded.to_string();
@@ -58,6 +600,36 @@ fn compute_pi(digits: u32) -> String {
     format!("{head}.{tail}")
 }
 
+// Rechecks sieve output with Miller-Rabin, which shares no code with the
+// sieve. Each value is checked with probability `rate` (from a fixed seed, so
+// reruns check the same ones); ordering is always checked. Returns how many
+// were tested and the indices that failed.
+fn verify_generated_primes(primes: &[usize], rate: f64) -> (usize, Vec<usize>) {
+    let mut rng = Rng::new(DEFAULT_SEED);
+    let mut checked = 0;
+    let mut failures = Vec::new();
+    for (index, &p) in primes.iter().enumerate() {
+        let out_of_order = index > 0 && primes[index - 1] >= p;
+        if rate < 1.0 && !out_of_order && rng.next_f64() >= rate {
+            continue;
+        }
+        checked += 1;
+        if out_of_order || !is_prime(p as u64) {
+            failures.push(index);
+        }
+    }
+    (checked, failures)
+}
+
+fn estimate_upper_bound(count: usize) -> usize {
+    // p_n < n (ln n + ln ln n) holds for every n >= 6.
+    if count < 6 {
+        return 15;
+    }
+    let c = count as f64;
+    ((c * (c.ln() + c.ln().ln())).ceil() as usize).max(15)
+}
+
 fn generate_primes(count: usize) -> Vec<usize> {
     if count == 0 {
         return vec![];
@@ -65,13 +637,8 @@ fn generate_primes(count: usize) -> Vec<usize> {
     if count == 1 {
         return vec![2];
     }
-    let upper = if count < 6 {
-        15
-    } else {
-        let c = count as f64;
-        (c * (c.ln() + c.ln().ln())).ceil() as usize
-    };
-    let limit = upper.max(15);
+    let limit = estimate_upper_bound(count);
+    verbose!(1, "sieve upper bound: {limit}");
     let mut sieve = vec![false; limit + 1];
     let mut primes = Vec::with_capacity(count);
     for i in 2..=limit {
@@ -91,98 +658,48 @@ fn generate_primes(count: usize) -> Vec<usize> {
     primes
 }
 
-fn bench_matmul(seconds: u64) -> String {
-    let n = 128usize;
-    let mut a = vec![1.001f64; n * n];
-    let mut b = vec![0.999f64; n * n];
-    let mut c = vec![0.0f64; n * n];
-    let start = Instant::now();
-    let mut iters: u64 = 0;
-    let duration = Duration::from_secs(seconds);
-    let sample_window = Duration::from_secs(1);
-    let mut samples: Vec<f64> = Vec::new();
-
-    while start.elapsed() < duration {
-        let sample_start = Instant::now();
-        let mut sample_iters: u64 = 0;
-        while sample_start.elapsed() < sample_window && start.elapsed() < duration {
-            for i in 0..n {
-                for k in 0..n {
-                    let aik = a[i * n + k];
-                    for j in 0..n {
-                        c[i * n + j] += aik * b[k * n + j];
-                    }
-                }
-            }
-            a[0] = c[0] / 3.14159;
-            sample_iters += 1;
-            iters += 1;
+fn unescape_delimiter(raw: &str) -> String {
+    let mut out = String::new();
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
         }
-        let sample_elapsed = sample_start.elapsed().as_secs_f64();
-        if sample_elapsed > 0.0 {
-            let flops = 2.0 * (n as f64).powi(3) * sample_iters as f64;
-            samples.push((flops / sample_elapsed) / 1.0e9);
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
         }
     }
-
-    let elapsed = start.elapsed().as_secs_f64();
-    let flops = 2.0 * (n as f64).powi(3) * iters as f64;
-    let gflops = (flops / elapsed) / 1.0e9;
-    let (min, avg, max) = stats(&samples);
-    format!(
-        "Iterations: {iters}\nGFLOP/s avg: {:.2}\nGFLOP/s min: {:.2}\nGFLOP/s max: {:.2}\nGFLOP/s overall: {:.2}\nSize: {n}x{n}",
-        avg, min, max, gflops
-    )
+    out
 }
 
-fn bench_bigint(seconds: u64) -> String {
-    use num_bigint::BigInt;
-    use num_traits::One;
-
-    let mut a = BigInt::one();
-    let mut b = BigInt::one();
-    for _ in 0..4096 {
-        a = &a * 10u32 + 7u32;
-        b = &b * 10u32 + 3u32;
-    }
-
-    let start = Instant::now();
-    let duration = Duration::from_secs(seconds);
-    let sample_window = Duration::from_secs(1);
-    let mut iters: u64 = 0;
-    let mut acc = BigInt::one();
-    let mut samples: Vec<f64> = Vec::new();
-
-    while start.elapsed() < duration {
-        let sample_start = Instant::now();
-        let mut sample_iters: u64 = 0;
-        while sample_start.elapsed() < sample_window && start.elapsed() < duration {
-            acc = &a * &b + &acc;
-            iters += 1;
-            sample_iters += 1;
-        }
-        let sample_elapsed = sample_start.elapsed().as_secs_f64();
-        if sample_elapsed > 0.0 {
-            samples.push(sample_iters as f64 / sample_elapsed);
-        }
+fn format_list<T: fmt::Display>(values: &[T], delimiter: &str, per_line: usize) -> String {
+    let join = |chunk: &[T]| {
+        chunk
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(delimiter)
+    };
+    if per_line == 0 {
+        return join(values);
     }
-
-    let elapsed = start.elapsed().as_secs_f64();
-    let per_sec = iters as f64 / elapsed;
-    let (min, avg, max) = stats(&samples);
-    format!(
-        "Iterations: {iters}\nMultiplies/sec avg: {:.2}\nMultiplies/sec min: {:.2}\nMultiplies/sec max: {:.2}\nMultiplies/sec overall: {:.2}\nDigits: {}",
-        avg, min, max, per_sec,
-        acc.to_string().len()
-    )
+    values
+        .chunks(per_line)
+        .map(join)
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-fn sieve_count(limit: usize) -> usize {
+fn sieve_primes(limit: usize) -> Vec<u64> {
     let mut sieve = vec![false; limit + 1];
-    let mut count = 0;
+    let mut primes = Vec::new();
     for i in 2..=limit {
         if !sieve[i] {
-            count += 1;
+            primes.push(i as u64);
             let mut j = i * i;
             while j <= limit {
                 sieve[j] = true;
@@ -190,95 +707,6009 @@ fn sieve_count(limit: usize) -> usize {
             }
         }
     }
-    count
+    primes
 }
 
-fn bench_sieve(seconds: u64) -> String {
-    let limit = 2_000_000usize;
-    let start = Instant::now();
-    let duration = Duration::from_secs(seconds);
-    let sample_window = Duration::from_secs(1);
-    let mut iters: u64 = 0;
-    let mut primes_count: usize = 0;
-    let mut samples: Vec<f64> = Vec::new();
+// mu[k] for 0 <= k <= limit (mu[0] is unused and left 0). A linear sieve
+// reaches each composite once, via its smallest prime factor p: mu(p*i) is
+// -mu(i) while p does not divide i, and 0 once p^2 divides p*i.
+fn mobius_sieve(limit: usize) -> Vec<i8> {
+    let mut mu = vec![0i8; limit + 1];
+    let mut composite = vec![false; limit + 1];
+    let mut primes: Vec<usize> = Vec::new();
+    if limit >= 1 {
+        mu[1] = 1;
+    }
+    for i in 2..=limit {
+        if !composite[i] {
+            primes.push(i);
+            mu[i] = -1;
+        }
+        for &p in &primes {
+            let Some(m) = i.checked_mul(p).filter(|&m| m <= limit) else {
+                break;
+            };
+            composite[m] = true;
+            if i.is_multiple_of(p) {
+                mu[m] = 0;
+                break;
+            }
+            mu[m] = -mu[i];
+        }
+    }
+    mu
+}
 
-    while start.elapsed() < duration {
-        let sample_start = Instant::now();
-        let mut sample_iters: u64 = 0;
-        while sample_start.elapsed() < sample_window && start.elapsed() < duration {
-            primes_count = sieve_count(limit);
-            iters += 1;
-            sample_iters += 1;
+fn mertens(n: usize) -> i64 {
+    mobius_sieve(n).iter().map(|&m| m as i64).sum()
+}
+
+// phi[k] for 0 <= k <= limit, by the same linear sieve as mobius_sieve:
+// phi(p*i) is phi(i)*p when p divides i and phi(i)*(p-1) otherwise.
+fn totient_sieve(limit: usize) -> Vec<u64> {
+    let mut phi = vec![0u64; limit + 1];
+    let mut primes: Vec<usize> = Vec::new();
+    if limit >= 1 {
+        phi[1] = 1;
+    }
+    for i in 2..=limit {
+        if phi[i] == 0 {
+            primes.push(i);
+            phi[i] = i as u64 - 1;
         }
-        let sample_elapsed = sample_start.elapsed().as_secs_f64();
-        if sample_elapsed > 0.0 {
-            samples.push(sample_iters as f64 / sample_elapsed);
+        for &p in &primes {
+            let Some(m) = i.checked_mul(p).filter(|&m| m <= limit) else {
+                break;
+            };
+            if i.is_multiple_of(p) {
+                phi[m] = phi[i] * p as u64;
+                break;
+            }
+            phi[m] = phi[i] * (p as u64 - 1);
         }
     }
+    phi
+}
 
-    let elapsed = start.elapsed().as_secs_f64();
-    let per_sec = iters as f64 / elapsed;
-    let (min, avg, max) = stats(&samples);
-    format!(
-        "Iterations: {iters}\nSieves/sec avg: {:.2}\nSieves/sec min: {:.2}\nSieves/sec max: {:.2}\nSieves/sec overall: {:.2}\nLimit: {limit}\nPrimes: {primes_count}",
-        avg, min, max, per_sec
-    )
+// Phi(n) = phi(1) + ... + phi(n), which is one less than the length of F_n.
+fn totient_sum(n: usize) -> u128 {
+    totient_sieve(n).iter().map(|&v| v as u128).sum()
 }
 
-fn stats(samples: &[f64]) -> (f64, f64, f64) {
-    if samples.is_empty() {
-        return (0.0, 0.0, 0.0);
+struct SmallPrimes {
+    primes: Arc<Vec<u64>>,
+    len: usize,
+}
+
+impl std::ops::Deref for SmallPrimes {
+    type Target = [u64];
+
+    fn deref(&self) -> &[u64] {
+        &self.primes[..self.len]
     }
-    let mut min = samples[0];
-    let mut max = samples[0];
-    let mut sum = 0.0;
-    for &v in samples {
-        if v < min {
-            min = v;
+}
+
+// Sieved once per process and grown on demand, so repeated factorizations
+// (including from benchmark threads) share the same base primes.
+static SMALL_PRIMES: OnceLock<Mutex<(u64, Arc<Vec<u64>>)>> = OnceLock::new();
+
+fn small_primes_up_to(limit: u64) -> SmallPrimes {
+    let cache = SMALL_PRIMES.get_or_init(|| Mutex::new((0, Arc::new(Vec::new()))));
+    let mut guard = cache.lock().unwrap_or_else(|e| e.into_inner());
+    if guard.0 < limit {
+        let target = limit.max(guard.0 * 2);
+        verbose!(1, "small primes: sieving up to {target}");
+        *guard = (target, Arc::new(sieve_primes(target as usize)));
+    }
+    let primes = Arc::clone(&guard.1);
+    let len = primes.partition_point(|&p| p <= limit);
+    SmallPrimes { primes, len }
+}
+
+fn gcd_u64(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn lcm_u64(a: u64, b: u64) -> u64 {
+    a / gcd_u64(a, b) * b
+}
+
+fn pollard_rho(n: u64) -> u64 {
+    if n.is_multiple_of(2) {
+        return 2;
+    }
+    for c in 1..n {
+        let step = |x: u64| ((mulmod(x, x, n) as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2u64, 2u64, 1u64);
+        while d == 1 {
+            x = step(x);
+            y = step(step(y));
+            d = gcd_u64(x.abs_diff(y), n);
         }
-        if v > max {
-            max = v;
+        if d != n {
+            return d;
         }
-        sum += v;
     }
-    let avg = sum / samples.len() as f64;
-    (min, avg, max)
+    n
 }
 
-fn main() {
-    let mut args = env::args().skip(1);
-    let cmd = args.next().unwrap_or_default();
-    match cmd.as_str() {
-        "pi" => {
-            let digits: u32 = args.next().unwrap_or("50".to_string()).parse().unwrap_or(50);
-            println!("{}", compute_pi(digits));
+const TRIAL_DIVISION_LIMIT: u64 = 1 << 16;
+
+fn factorize(n: u64) -> Vec<(u64, u32)> {
+    let mut factors = Vec::new();
+    let mut rest = n;
+    for &p in small_primes_up_to(TRIAL_DIVISION_LIMIT).iter() {
+        if p * p > rest {
+            break;
         }
-        "primes" => {
-            let count: usize = args.next().unwrap_or("15".to_string()).parse().unwrap_or(15);
-            let primes = generate_primes(count);
-            let line = primes
-                .into_iter()
-                .map(|p| p.to_string())
-                .collect::<Vec<_>>()
-                .join(", ");
-            println!("{line}");
+        let mut exp = 0;
+        while rest.is_multiple_of(p) {
+            rest /= p;
+            exp += 1;
         }
-        "bench-matmul" => {
-            let seconds: u64 = args.next().unwrap_or("60".to_string()).parse().unwrap_or(60);
-            println!("{}", bench_matmul(seconds));
+        if exp > 0 {
+            factors.push((p, exp));
         }
-        "bench-bigint" => {
-            let seconds: u64 = args.next().unwrap_or("60".to_string()).parse().unwrap_or(60);
-            println!("{}", bench_bigint(seconds));
+    }
+    if rest > 1 {
+        let mut pending = vec![rest];
+        let mut large = Vec::new();
+        while let Some(m) = pending.pop() {
+            if m < TRIAL_DIVISION_LIMIT * TRIAL_DIVISION_LIMIT || is_prime(m) {
+                large.push(m);
+            } else {
+                let d = pollard_rho(m);
+                pending.push(d);
+                pending.push(m / d);
+            }
         }
-        "bench-sieve" => {
-            let seconds: u64 = args.next().unwrap_or("60".to_string()).parse().unwrap_or(60);
-            println!("{}", bench_sieve(seconds));
+        large.sort_unstable();
+        for p in large {
+            match factors.last_mut() {
+                Some((q, exp)) if *q == p => *exp += 1,
+                _ => factors.push((p, 1)),
+            }
         }
-        _ => {
-            eprintln!(
-                "usage: gensuite-helper [pi <digits>|primes <count>|bench-matmul <sec>|bench-bigint <sec>|bench-sieve <sec>]"
-            );
+    }
+    factors
+}
+
+fn format_factorization(n: u64, factors: &[(u64, u32)]) -> String {
+    if factors.is_empty() {
+        return format!("{n} = {n}");
+    }
+    let parts: Vec<String> = factors
+        .iter()
+        .map(|&(p, e)| {
+            if e == 1 {
+                p.to_string()
+            } else {
+                format!("{p}^{e}")
+            }
+        })
+        .collect();
+    format!("{n} = {}", parts.join(" * "))
+}
+
+fn jacobi_symbol(a: i64, n: u64) -> i8 {
+    assert!(n % 2 == 1, "the Jacobi symbol needs an odd positive n");
+    let mut a = (a as i128).rem_euclid(n as i128) as u64;
+    let mut n = n;
+    let mut result = 1i8;
+    while a != 0 {
+        // (2/n) is -1 exactly when n is 3 or 5 mod 8.
+        while a.is_multiple_of(2) {
+            a /= 2;
+            if matches!(n % 8, 3 | 5) {
+                result = -result;
+            }
+        }
+        // Quadratic reciprocity: swapping flips the sign when both are 3 mod 4.
+        (a, n) = (n, a);
+        if a % 4 == 3 && n % 4 == 3 {
+            result = -result;
+        }
+        a %= n;
+    }
+    if n == 1 {
+        result
+    } else {
+        0
+    }
+}
+
+// Extends the Jacobi symbol to every n: (a/0) is 1 only for a = ±1, (a/-1)
+// is the sign of a, and (a/2) is 0 for even a, else 1 when a is ±1 mod 8 and
+// -1 when it is ±3 mod 8. The symbol is multiplicative in n, so the odd part
+// is left to jacobi_symbol.
+fn kronecker_symbol(a: i64, n: i64) -> i8 {
+    if n == 0 {
+        return if a == 1 || a == -1 { 1 } else { 0 };
+    }
+    let mut result = if n < 0 && a < 0 { -1 } else { 1 };
+    let mut odd = n.unsigned_abs();
+    let twos = odd.trailing_zeros();
+    if twos > 0 {
+        if a % 2 == 0 {
+            return 0;
+        }
+        odd >>= twos;
+        if twos % 2 == 1 && matches!(a.rem_euclid(8), 3 | 5) {
+            result = -result;
+        }
+    }
+    result * jacobi_symbol(a, odd)
+}
+
+fn mulmod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn modpow(base: u64, mut exp: u64, m: u64) -> u64 {
+    if m == 1 {
+        return 0;
+    }
+    let mut base = base % m;
+    let mut result = 1u64;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mulmod(result, base, m);
+        }
+        base = mulmod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+// a^-1 mod m from the extended Euclidean algorithm, reduced to [0, m); None
+// when gcd(a, m) != 1. m must be positive, a may be negative.
+fn mod_inverse(a: num_bigint::BigInt, m: num_bigint::BigInt) -> Option<num_bigint::BigInt> {
+    use num_integer::Integer;
+    use num_traits::{One, Zero};
+
+    // Invariant: old_s * a = old_r and s * a = r (mod m).
+    let (mut old_r, mut r) = (a.mod_floor(&m), m.clone());
+    let (mut old_s, mut s) = (num_bigint::BigInt::one(), num_bigint::BigInt::zero());
+    while !r.is_zero() {
+        let (q, rem) = old_r.div_rem(&r);
+        old_r = std::mem::replace(&mut r, rem);
+        let next_s = &old_s - &q * &s;
+        old_s = std::mem::replace(&mut s, next_s);
+    }
+    old_r.is_one().then(|| old_s.mod_floor(&m))
+}
+
+fn euler_phi(n: u64) -> u64 {
+    factorize(n)
+        .iter()
+        .fold(n, |phi, &(p, _)| phi / p * (p - 1))
+}
+
+// rad(n), the product of the distinct primes dividing n; rad(1) = 1.
+fn radical(n: u64) -> u64 {
+    factorize(n).iter().map(|&(p, _)| p).product()
+}
+
+// lambda(n), the exponent of the group of units mod n: the lcm over the
+// prime powers of n of lambda(p^e) = phi(p^e), except that for 2^e with
+// e >= 3 the group is not cyclic and lambda is half of phi.
+fn carmichael_lambda(n: u64) -> u64 {
+    factorize(n).iter().fold(1, |lambda, &(p, e)| {
+        let phi = p.pow(e - 1) * (p - 1);
+        let part = if p == 2 && e >= 3 { phi / 2 } else { phi };
+        lcm_u64(lambda, part)
+    })
+}
+
+// sigma(n), the sum of all divisors, from sigma(p^e) = (p^(e+1) - 1)/(p - 1).
+fn divisor_sum(n: u64) -> u64 {
+    factorize(n)
+        .iter()
+        .map(|&(p, e)| (0..=e).fold(0, |sum, _| sum * p + 1))
+        .product()
+}
+
+// d(n), the number of divisors: the product of (e + 1) over the factorization.
+fn divisor_count(n: u64) -> u64 {
+    factorize(n).iter().map(|&(_, e)| e as u64 + 1).product()
+}
+
+// Numbers up to the bound with more divisors than every smaller number.
+fn highly_composite(bound: u64) -> Vec<u64> {
+    let mut record = 0;
+    (1..=bound)
+        .filter(|&n| {
+            let count = divisor_count(n);
+            let is_record = count > record;
+            record = record.max(count);
+            is_record
+        })
+        .collect()
+}
+
+// Pairs a < b with s(a) = b and s(b) = a, where s(n) = sigma(n) - n is the
+// aliquot sum. b may exceed the bound; only a has to lie below it.
+fn amicable_pairs(bound: u64) -> Vec<(u64, u64)> {
+    (2..bound)
+        .filter_map(|a| {
+            let b = divisor_sum(a) - a;
+            (b > a && divisor_sum(b) - b == a).then_some((a, b))
+        })
+        .collect()
+}
+
+// The pair scan is quadratic in the bound; at this size it takes seconds.
+const ABC_MAX_BOUND: u64 = 100_000;
+
+// Coprime a < b with c = a + b <= bound and quality ln(c)/ln(rad(abc)) above
+// min_quality, best first. Coprimality makes rad(abc) = rad(a) rad(b) rad(c),
+// and q > t means rad(abc) < c^(1/t), so pairs whose rad(a) rad(c) already
+// reach that are dropped before the gcd.
+fn abc_triples(bound: u64, min_quality: f64) -> Vec<(u64, u64, u64, f64)> {
+    let radicals: Vec<u64> = (0..=bound)
+        .map(|n| if n == 0 { 0 } else { radical(n) })
+        .collect();
+    let mut triples = Vec::new();
+    for c in 3..=bound {
+        let limit = (c as f64).powf(1.0 / min_quality);
+        let rad_c = radicals[c as usize];
+        for a in 1..c.div_ceil(2) {
+            let b = c - a;
+            let partial = radicals[a as usize] * rad_c;
+            if partial as f64 >= limit {
+                continue;
+            }
+            let rad = partial as f64 * radicals[b as usize] as f64;
+            if rad >= limit || gcd_u64(a, b) != 1 {
+                continue;
+            }
+            let quality = (c as f64).ln() / rad.ln();
+            if quality > min_quality {
+                triples.push((a, b, c, quality));
+            }
+        }
+    }
+    triples.sort_by(|x, y| y.3.total_cmp(&x.3).then(x.2.cmp(&y.2)));
+    triples
+}
+
+// The order divides phi(n), so start from phi(n) and strip each prime
+// factor for as long as a^(k/p) is still 1.
+fn multiplicative_order(a: u64, n: u64) -> Option<u64> {
+    assert!(n > 0, "order modulo zero");
+    if gcd_u64(a % n, n) != 1 {
+        return None;
+    }
+    if n == 1 {
+        return Some(1);
+    }
+    let phi = euler_phi(n);
+    let mut order = phi;
+    for (p, _) in factorize(phi) {
+        while order.is_multiple_of(p) && modpow(a, order / p, n) == 1 {
+            order /= p;
+        }
+    }
+    Some(order)
+}
+
+// These bases make Miller-Rabin deterministic for every n < 2^64.
+const MR_BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for &p in &MR_BASES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    'bases: for &a in &MR_BASES {
+        let mut x = modpow(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mulmod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// Bases tried by Solovay-Strassen beyond the fixed MR_BASES.
+const SOLOVAY_RANDOM_BASES: usize = 20;
+
+fn legendre_symbol(a: i64, p: u64) -> i8 {
+    assert!(
+        p > 2 && is_prime(p),
+        "the Legendre symbol needs an odd prime p"
+    );
+    euler_criterion((a as i128).rem_euclid(p as i128) as u64, p)
+}
+
+// For an odd prime p, a^((p-1)/2) is 1 for a quadratic residue, p - 1 for a
+// non-residue and 0 when p divides a.
+fn euler_criterion(a: u64, p: u64) -> i8 {
+    match modpow(a, (p - 1) / 2, p) {
+        0 => 0,
+        1 => 1,
+        _ => -1,
+    }
+}
+
+// A square root of a modulo a prime p, or None for a non-residue.
+fn tonelli_shanks(a: u64, p: u64) -> Option<u64> {
+    let a = a % p;
+    if p == 2 || a == 0 {
+        return Some(a);
+    }
+    // Residues of 64-bit primes need not fit an i64, so this skips
+    // legendre_symbol's conversion.
+    if euler_criterion(a, p) != 1 {
+        return None;
+    }
+    if p % 4 == 3 {
+        return Some(modpow(a, (p + 1) / 4, p));
+    }
+    // p - 1 = q * 2^s with q odd, and z is any non-residue.
+    let s = (p - 1).trailing_zeros();
+    let q = (p - 1) >> s;
+    let mut z = 2;
+    while euler_criterion(z, p) != -1 {
+        z += 1;
+    }
+    let mut m = s;
+    let mut c = modpow(z, q, p);
+    let mut t = modpow(a, q, p);
+    let mut r = modpow(a, q.div_ceil(2), p);
+    // Invariant: r^2 = a*t, and t has order dividing 2^(m-1).
+    while t != 1 {
+        let mut i = 0;
+        let mut t2i = t;
+        while t2i != 1 {
+            t2i = mulmod(t2i, t2i, p);
+            i += 1;
+        }
+        let b = modpow(c, 1 << (m - i - 1), p);
+        m = i;
+        c = mulmod(b, b, p);
+        t = mulmod(t, c, p);
+        r = mulmod(r, b, p);
+    }
+    Some(r)
+}
+
+// A prime p = 1 mod 4 as a² + b², a <= b (Hermite-Serret): run Euclid on p
+// and a square root of -1 mod p; the first remainder below sqrt(p) is a.
+fn prime_as_two_squares(p: u64) -> (u64, u64) {
+    let root = tonelli_shanks(p - 1, p).expect("-1 is a square mod p = 1 mod 4");
+    let (mut r0, mut r1) = (p, root);
+    while r1 as u128 * r1 as u128 > p as u128 {
+        (r0, r1) = (r1, r0 % r1);
+    }
+    let b = (p - r1 * r1).isqrt();
+    (r1.min(b), r1.max(b))
+}
+
+// x² + y² = n with x <= y, or None when some prime 3 mod 4 divides n to an
+// odd power. Each prime 1 mod 4 contributes its Gaussian factor a + bi once
+// per power, 2 contributes 1 + i, and each pair of 3 mod 4 primes a real p;
+// the norm of the product is n.
+fn as_sum_of_two_squares(n: u64) -> Option<(u64, u64)> {
+    if n == 0 {
+        return Some((0, 0));
+    }
+    let (mut re, mut im) = (1i128, 0i128);
+    let mut times = |a: i128, b: i128| (re, im) = (re * a - im * b, re * b + im * a);
+    for (p, e) in factorize(n) {
+        match p % 4 {
+            3 if e % 2 == 1 => return None,
+            3 => (0..e / 2).for_each(|_| times(p as i128, 0)),
+            1 => {
+                let (a, b) = prime_as_two_squares(p);
+                (0..e).for_each(|_| times(a as i128, b as i128));
+            }
+            _ => (0..e).for_each(|_| times(1, 1)),
+        }
+    }
+    let (x, y) = (re.unsigned_abs() as u64, im.unsigned_abs() as u64);
+    Some((x.min(y), x.max(y)))
+}
+
+fn is_prime_solovay(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for &p in &MR_BASES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    // Unlike Miller-Rabin, no fixed set of bases is known to be exact below
+    // 2^64, so a few pseudorandom ones (seeded by n, to keep answers stable)
+    // back up the fixed primes. Each base a passes a composite with
+    // probability at most 1/2, via Euler's criterion a^((n-1)/2) = (a/n).
+    let mut rng = Rng::new(n);
+    let top = (n - 2).min(i64::MAX as u64);
+    let random = (0..SOLOVAY_RANDOM_BASES).map(|_| 2 + rng.below(top - 1));
+    MR_BASES.iter().copied().chain(random).all(|a| {
+        let symbol = jacobi_symbol(a as i64, n);
+        let expected = match symbol {
+            1 => 1,
+            -1 => n - 1,
+            _ => return false,
+        };
+        modpow(a, (n - 1) / 2, n) == expected
+    })
+}
+
+fn log_integral(x: f64) -> f64 {
+    // li(2), so the integral can start past the singularity at t = 1.
+    const LI_2: f64 = 1.045_163_780_117_493;
+    if x <= 2.0 {
+        return LI_2;
+    }
+    // With t = e^u the integrand 1/ln t becomes e^u / u, which is smooth on
+    // [ln 2, ln x], so composite Simpson converges quickly.
+    let steps = 10_000;
+    let (lo, hi) = (2f64.ln(), x.ln());
+    let h = (hi - lo) / steps as f64;
+    let f = |u: f64| u.exp() / u;
+    let inner: f64 = (1..steps)
+        .map(|i| {
+            let weight = if i % 2 == 1 { 4.0 } else { 2.0 };
+            weight * f(lo + i as f64 * h)
+        })
+        .sum();
+    LI_2 + h / 3.0 * (f(lo) + inner + f(hi))
+}
+
+fn palindromic_primes(count: usize) -> Vec<u64> {
+    let mut found: Vec<u64> = [2, 3, 5, 7, 11].into_iter().take(count).collect();
+    // Every even-length palindrome is a multiple of 11, so past 11 only odd
+    // lengths are built: each prefix is mirrored around its own last digit.
+    // Prefixes increase monotonically, so the palindromes come out in order.
+    // u64 holds palindromes up to 19 digits, i.e. prefixes of up to 10.
+    for half in 2..=10u32 {
+        for prefix in 10u64.pow(half - 1)..10u64.pow(half) {
+            if found.len() >= count {
+                return found;
+            }
+            // The leading digit is also the last, so it has to be odd and not 5.
+            let lead = prefix / 10u64.pow(half - 1);
+            if lead.is_multiple_of(2) || lead == 5 {
+                continue;
+            }
+            let mut value = prefix;
+            let mut rest = prefix / 10;
+            while rest > 0 {
+                value = value * 10 + rest % 10;
+                rest /= 10;
+            }
+            if is_prime(value) {
+                found.push(value);
+            }
+        }
+    }
+    found
+}
+
+// a(28) is a 27-digit prime, far beyond what Pollard rho can reach.
+const EUCLID_MULLIN_MAX_TERMS: usize = 27;
+// From a(17) on, each term needs rho on a cofactor of 40+ digits.
+const EUCLID_MULLIN_CHEAP_TERMS: usize = 16;
+
+// Each term comes with whether it is certain; see smallest_prime_factor_big.
+fn euclid_mullin(count: usize) -> Vec<(num_bigint::BigInt, bool)> {
+    use num_bigint::BigInt;
+
+    let mut terms: Vec<(BigInt, bool)> = Vec::with_capacity(count);
+    let mut product = BigInt::from(1u32);
+    for index in 1..=count {
+        let (term, certain) = smallest_prime_factor_big(&(&product + 1u32));
+        verbose!(1, "euclid-mullin: a({index}) = {term}");
+        product *= &term;
+        terms.push((term, certain));
+    }
+    terms
+}
+
+fn repunit_primes(bound: u64) -> Vec<u64> {
+    // R_n = (10^n - 1) / 9. When n = ab, R_a divides R_n, so only prime n
+    // can give a prime repunit.
+    sieve_primes(bound as usize)
+        .into_iter()
+        .filter(|&n| {
+            let repunit = (pow10(n as u32) - 1u32) / 9u32;
+            verbose!(1, "repunit R_{n}: testing");
+            is_probable_prime_big(&repunit)
+        })
+        .collect()
+}
+
+// The Wieferich scan sieves the whole range, one byte per number.
+const WIEFERICH_MAX_BOUND: u64 = 100_000_000;
+// Each Wilson candidate costs p modular multiplications, so the whole scan
+// grows with bound^2 / ln(bound); 10^5 takes a few seconds.
+const WILSON_MAX_BOUND: u64 = 100_000;
+
+// Primes with 2^(p-1) = 1 (mod p^2); only 1093 and 3511 are known.
+fn wieferich_primes(bound: u64) -> Vec<u64> {
+    sieve_primes(bound as usize)
+        .into_iter()
+        .filter(|&p| modpow(2, p - 1, p * p) == 1)
+        .collect()
+}
+
+// Primes with (p-1)! = -1 (mod p^2); only 5, 13 and 563 are known.
+fn wilson_primes(bound: u64) -> Vec<u64> {
+    sieve_primes(bound as usize)
+        .into_iter()
+        .filter(|&p| {
+            let square = p * p;
+            let factorial = (2..p).fold(1 % square, |acc, k| mulmod(acc, k, square));
+            factorial == square - 1
+        })
+        .collect()
+}
+
+// The scan sieves [lo, hi] one byte per number after sieving the base
+// primes up to sqrt(hi), so both limits keep it near 100 MB.
+const PSEUDOPRIMES_MAX_SPAN: u64 = 100_000_000;
+const PSEUDOPRIMES_MAX_BOUND: u64 = 10_000_000_000_000_000;
+
+fn fermat_pseudoprimes(lo: u64, hi: u64) -> Vec<u64> {
+    let lo = lo.max(3);
+    if lo > hi {
+        return Vec::new();
+    }
+    // The Fermat test alone cannot tell a pseudoprime from a prime, so the
+    // sieve over the same range supplies compositeness independently.
+    let base = sieve_primes(isqrt_usize(hi as usize));
+    let mut composite = vec![false; (hi - lo + 1) as usize];
+    mark_composites(lo as usize, &base, &mut composite);
+    (lo..=hi)
+        .zip(composite)
+        // Even n never passes: 2^(n-1) mod n is then even.
+        .filter(|&(n, composite)| composite && !n.is_multiple_of(2) && modpow(2, n - 1, n) == 1)
+        .map(|(n, _)| n)
+        .collect()
+}
+
+fn is_probable_prime_big(n: &num_bigint::BigInt) -> bool {
+    use num_bigint::BigInt;
+    use num_traits::{One, ToPrimitive};
+
+    if let Some(small) = n.to_u64() {
+        return is_prime(small);
+    }
+    if n.sign() == num_bigint::Sign::Minus {
+        return false;
+    }
+    for &p in &MR_BASES {
+        if (n % p).to_u64() == Some(0) {
+            return false;
+        }
+    }
+    let one = BigInt::one();
+    let n_minus_1 = n - &one;
+    let s = n_minus_1.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_1 >> s;
+    'bases: for &a in &MR_BASES {
+        let mut x = BigInt::from(a).modpow(&d, n);
+        if x == one || x == n_minus_1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == n_minus_1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// Draws odd `bits`-bit candidates (top bit set) until one passes
+// Miller-Rabin. Returns the prime and how many candidates were tried.
+fn random_prime(bits: u64, rng: &mut Rng) -> (num_bigint::BigInt, u64) {
+    use num_bigint::{BigInt, Sign};
+
+    let words = bits.div_ceil(64);
+    for tries in 1.. {
+        let bytes: Vec<u8> = (0..words)
+            .flat_map(|_| rng.next_u64().to_le_bytes())
+            .collect();
+        let mut candidate = BigInt::from_bytes_le(Sign::Plus, &bytes) >> (words * 64 - bits);
+        candidate.set_bit(bits - 1, true);
+        candidate.set_bit(0, true);
+        if is_probable_prime_big(&candidate) {
+            return (candidate, tries);
+        }
+    }
+    unreachable!("the candidate loop only ends by returning")
+}
+
+// Brent's variant of Pollard's rho, with the gcds batched over runs of
+// RHO_BATCH steps. Returns a nontrivial factor of the odd composite n, or
+// None once `budget` steps have passed without finding one.
+const RHO_BATCH: u64 = 128;
+
+fn pollard_rho_big(n: &num_bigint::BigInt, budget: u64) -> Option<num_bigint::BigInt> {
+    use num_bigint::BigInt;
+    use num_integer::Integer;
+    use num_traits::One;
+
+    let one = BigInt::one();
+    let mut steps = 0u64;
+    for c in 1u32.. {
+        let step = |x: &BigInt| (x * x + c) % n;
+        let mut y = BigInt::from(2u32);
+        let mut x = y.clone();
+        let mut saved = y.clone();
+        let mut q = one.clone();
+        let mut g = one.clone();
+        let mut r = 1u64;
+        while g == one {
+            if steps > budget {
+                return None;
+            }
+            x = y.clone();
+            for _ in 0..r {
+                y = step(&y);
+            }
+            let mut k = 0;
+            while k < r && g == one {
+                saved = y.clone();
+                for _ in 0..RHO_BATCH.min(r - k) {
+                    y = step(&y);
+                    q = q * (&x - &y) % n;
+                }
+                g = q.gcd(n);
+                k += RHO_BATCH;
+            }
+            steps += 2 * r;
+            r *= 2;
+        }
+        // The batch overshot the collision: replay it one step at a time.
+        if g == *n {
+            loop {
+                saved = step(&saved);
+                g = (&x - &saved).gcd(n);
+                if g != one {
+                    break;
+                }
+            }
+        }
+        if g != *n {
+            return Some(g);
+        }
+    }
+    unreachable!("every odd composite has a rho factor for some c")
+}
+
+// Rho finds a prime factor p after about sqrt(p) steps, so a composite that
+// survives this many times sqrt(best) steps is taken to have no factor below
+// the best prime found so far.
+const RHO_CONFIDENCE: u64 = 64;
+
+// The flag is false when a leftover composite could not be split within the
+// bounded search, so a smaller prime factor may still hide in it.
+fn smallest_prime_factor_big(n: &num_bigint::BigInt) -> (num_bigint::BigInt, bool) {
+    use num_bigint::BigInt;
+    use num_integer::Integer;
+    use num_traits::ToPrimitive;
+
+    if let Some(small) = n.to_u64() {
+        return (
+            BigInt::from(factorize(small).first().map_or(small, |&(p, _)| p)),
+            true,
+        );
+    }
+    for &p in small_primes_up_to(TRIAL_DIVISION_LIMIT).iter() {
+        if n.is_multiple_of(&BigInt::from(p)) {
+            return (BigInt::from(p), true);
+        }
+    }
+    // No factor below the trial limit: split, keeping the least prime. Once
+    // one is known, the remaining composites only get a bounded search, as a
+    // full split of a large cofactor can take practically forever.
+    let mut pending = vec![n.clone()];
+    let mut least: Option<BigInt> = None;
+    let mut certain = true;
+    while let Some(m) = pending.pop() {
+        if is_probable_prime_big(&m) {
+            if least.as_ref().is_none_or(|best| m < *best) {
+                least = Some(m);
+            }
+            continue;
+        }
+        let budget = least.as_ref().map_or(u64::MAX, |best| {
+            isqrt(best).to_u64().unwrap_or(u64::MAX / RHO_CONFIDENCE) * RHO_CONFIDENCE
+        });
+        if let Some(d) = pollard_rho_big(&m, budget) {
+            pending.push(&m / &d);
+            pending.push(d);
+        } else {
+            verbose!(1, "rho gave up on a {}-bit cofactor", m.bits());
+            certain = false;
+        }
+    }
+    (least.unwrap_or_else(|| n.clone()), certain)
+}
+
+enum PrimeListCheck {
+    AllPrime(usize),
+    NotPrime {
+        value: String,
+        index: usize,
+        line: usize,
+        column: usize,
+    },
+    ParseError {
+        token: String,
+        line: usize,
+        column: usize,
+    },
+}
+
+fn verify_prime_list(text: &str) -> PrimeListCheck {
+    let mut index = 0;
+    for (line_no, line) in text.lines().enumerate() {
+        let mut column = 0;
+        for token in line.split(|c: char| c.is_whitespace() || c == ',') {
+            let token_column = column + 1;
+            column += token.chars().count() + 1;
+            if token.is_empty() {
+                continue;
+            }
+            index += 1;
+            let Ok(value) = token.parse::<num_bigint::BigInt>() else {
+                return PrimeListCheck::ParseError {
+                    token: token.to_string(),
+                    line: line_no + 1,
+                    column: token_column,
+                };
+            };
+            if !is_probable_prime_big(&value) {
+                return PrimeListCheck::NotPrime {
+                    value: token.to_string(),
+                    index,
+                    line: line_no + 1,
+                    column: token_column,
+                };
+            }
+        }
+    }
+    PrimeListCheck::AllPrime(index)
+}
+
+fn reduce_fraction(
+    num: num_bigint::BigInt,
+    den: num_bigint::BigInt,
+) -> (num_bigint::BigInt, num_bigint::BigInt) {
+    use num_integer::Integer;
+    use num_traits::Signed;
+
+    let g = num.gcd(&den);
+    let (num, den) = (num / &g, den / &g);
+    if den.is_negative() {
+        (-num, -den)
+    } else {
+        (num, den)
+    }
+}
+
+fn bernoulli(n: u32) -> (num_bigint::BigInt, num_bigint::BigInt) {
+    use num_bigint::BigInt;
+
+    // Akiyama-Tanigawa: row[j] holds A(m, j) as a reduced fraction.
+    let mut row: Vec<(BigInt, BigInt)> = Vec::with_capacity(n as usize + 1);
+    for m in 0..=n {
+        row.push((BigInt::from(1u32), BigInt::from(m + 1)));
+        for j in (1..=m as usize).rev() {
+            let (n1, d1) = &row[j - 1];
+            let (n2, d2) = &row[j];
+            let num = (n1 * d2 - n2 * d1) * j;
+            let den = d1 * d2;
+            row[j - 1] = reduce_fraction(num, den);
+        }
+    }
+    let (num, den) = row.swap_remove(0);
+    // The recurrence yields B_1 = +1/2; use the more common -1/2 convention.
+    if n == 1 {
+        (-num, den)
+    } else {
+        (num, den)
+    }
+}
+
+fn fraction_to_decimal(num: &num_bigint::BigInt, den: &num_bigint::BigInt, places: u32) -> String {
+    use num_traits::Signed;
+
+    let scaled = (num.abs() * pow10(places) * 2u32 + den.abs()) / (den.abs() * 2u32);
+    let mut digits = format!(
+        "{:0>width$}",
+        scaled.to_string(),
+        width = places as usize + 1
+    );
+    if places > 0 {
+        digits.insert(digits.len() - places as usize, '.');
+    }
+    if num.is_negative() != den.is_negative() && scaled.sign() != num_bigint::Sign::NoSign {
+        digits.insert(0, '-');
+    }
+    digits
+}
+
+fn harmonic_exact(n: u64) -> (num_bigint::BigInt, num_bigint::BigInt) {
+    use num_bigint::BigInt;
+
+    let mut num = BigInt::from(0u32);
+    let mut den = BigInt::from(1u32);
+    for k in 1..=n {
+        let next_num = &num * k + &den;
+        let next_den = den * k;
+        (num, den) = reduce_fraction(next_num, next_den);
+    }
+    (num, den)
+}
+
+fn harmonic_float(n: u64) -> f64 {
+    (1..=n).fold(0.0, |acc, k| acc + 1.0 / k as f64)
+}
+
+fn binomial(n: u64, k: u64) -> num_bigint::BigInt {
+    use num_bigint::BigInt;
+
+    if k > n {
+        return BigInt::from(0u32);
+    }
+    let k = k.min(n - k);
+    let mut result = BigInt::from(1u32);
+    // After step i the value is C(n - k + i, i), so every division is exact.
+    for i in 1..=k {
+        result *= n - k + i;
+        result /= i;
+    }
+    result
+}
+
+fn pascal_row(n: u64) -> Vec<num_bigint::BigInt> {
+    use num_bigint::BigInt;
+
+    // C(n, k + 1) = C(n, k) * (n - k) / (k + 1); the division is exact.
+    let mut row = Vec::with_capacity(n as usize + 1);
+    let mut entry = BigInt::from(1u32);
+    for k in 0..n {
+        let next = &entry * (n - k) / (k + 1);
+        row.push(entry);
+        entry = next;
+    }
+    row.push(entry);
+    row
+}
+
+fn partition_count(n: u64) -> num_bigint::BigInt {
+    use num_bigint::BigInt;
+
+    // Euler's pentagonal number theorem:
+    //   p(m) = sum over k >= 1 of (-1)^(k+1) [p(m - k(3k-1)/2) + p(m - k(3k+1)/2)]
+    // Each p(m) needs about sqrt(m) terms, so the whole table is O(n^1.5).
+    let n = n as usize;
+    let mut p: Vec<BigInt> = Vec::with_capacity(n + 1);
+    p.push(BigInt::from(1u32));
+    for m in 1..=n {
+        let mut total = BigInt::from(0u32);
+        for k in 1.. {
+            let first = k * (3 * k - 1) / 2;
+            if first > m {
+                break;
+            }
+            let second = k * (3 * k + 1) / 2;
+            let mut term = p[m - first].clone();
+            if second <= m {
+                term += &p[m - second];
+            }
+            if k % 2 == 1 {
+                total += term;
+            } else {
+                total -= term;
+            }
+        }
+        p.push(total);
+    }
+    p.swap_remove(n)
+}
+
+fn product_range(lo: u64, hi: u64) -> num_bigint::BigInt {
+    use num_bigint::BigInt;
+
+    if lo > hi {
+        return BigInt::from(1u32);
+    }
+    if hi - lo < 16 {
+        return (lo..=hi).fold(BigInt::from(1u32), |acc, k| acc * k);
+    }
+    // Splitting in halves keeps the operands balanced, which is where the
+    // faster BigInt multiplication algorithms pay off.
+    let mid = lo + (hi - lo) / 2;
+    product_range(lo, mid) * product_range(mid + 1, hi)
+}
+
+fn factorial(n: u64) -> num_bigint::BigInt {
+    product_range(2, n)
+}
+
+// The --low-memory factorial: factors are packed into a u64 until the next
+// one would overflow it, then multiplied into a single accumulator in place.
+// Only the accumulator and its growth are ever live, against the product
+// tree's two half-size operands and their product, at the price of
+// quadratic time.
+fn factorial_low_memory(n: u64) -> num_bigint::BigInt {
+    let mut acc = num_bigint::BigInt::from(1u32);
+    let mut word = 1u64;
+    for k in 2..=n {
+        match word.checked_mul(k) {
+            Some(packed) => word = packed,
+            None => {
+                acc *= word;
+                word = k;
+            }
+        }
+    }
+    acc *= word;
+    acc
+}
+
+// The process's high-water resident set (VmHWM), in KiB; Linux only.
+fn peak_memory_kib() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn report_peak_memory(what: &str) {
+    if let Some(kib) = peak_memory_kib() {
+        verbose!(
+            1,
+            "{what}: peak memory about {:.1} MiB",
+            kib as f64 / 1024.0
+        );
+    }
+}
+
+// Accepts a plain integer, `base^exponent` or `n!`.
+fn parse_big_expression(text: &str) -> Result<num_bigint::BigInt, String> {
+    let text = text.trim();
+    let invalid = || format!("cannot parse {text:?}: expected an integer, a^b or n!");
+    if let Some(n) = text.strip_suffix('!') {
+        return n.trim().parse().map(factorial).map_err(|_| invalid());
+    }
+    if let Some((base, exponent)) = text.split_once('^') {
+        let base: num_bigint::BigInt = base.trim().parse().map_err(|_| invalid())?;
+        let exponent: usize = exponent.trim().parse().map_err(|_| invalid())?;
+        return Ok(num_traits::pow(base, exponent));
+    }
+    text.parse().map_err(|_| invalid())
+}
+
+fn digit_sum(n: &num_bigint::BigInt) -> u64 {
+    n.magnitude()
+        .to_str_radix(10)
+        .bytes()
+        .map(|digit| (digit - b'0') as u64)
+        .sum()
+}
+
+fn digital_root(n: &num_bigint::BigInt) -> u64 {
+    let mut root = digit_sum(n);
+    while root >= 10 {
+        root = digit_sum(&root.into());
+    }
+    root
+}
+
+fn lcm_1_to_n(n: u64) -> num_bigint::BigInt {
+    use num_bigint::BigInt;
+
+    // lcm(1..=n) is the product of p^k over primes p <= n, with p^k the
+    // largest power not exceeding n.
+    let powers: Vec<BigInt> = sieve_primes(n as usize)
+        .into_iter()
+        .map(|p| {
+            let mut power = p;
+            while let Some(next) = power.checked_mul(p).filter(|&next| next <= n) {
+                power = next;
+            }
+            BigInt::from(power)
+        })
+        .collect();
+    product_of(&powers)
+}
+
+fn product_of(values: &[num_bigint::BigInt]) -> num_bigint::BigInt {
+    use num_bigint::BigInt;
+
+    match values {
+        [] => BigInt::from(1u32),
+        [single] => single.clone(),
+        _ => {
+            let (left, right) = values.split_at(values.len() / 2);
+            product_of(left) * product_of(right)
+        }
+    }
+}
+
+fn reciprocal_decimal(n: u64) -> (String, usize) {
+    assert!(n > 0, "reciprocal of zero");
+    if n == 1 {
+        return ("1".to_string(), 0);
+    }
+    // The count of leading non-repeating digits is the larger exponent of 2
+    // or 5 in n; when nothing else remains the expansion terminates there.
+    let mut rest = n;
+    let (mut twos, mut fives) = (0usize, 0usize);
+    while rest.is_multiple_of(2) {
+        rest /= 2;
+        twos += 1;
+    }
+    while rest.is_multiple_of(5) {
+        rest /= 5;
+        fives += 1;
+    }
+    let prefix_len = twos.max(fives);
+
+    let mut digits = String::from("0.");
+    let mut remainder: u128 = 1;
+    let n128 = n as u128;
+    for _ in 0..prefix_len {
+        remainder *= 10;
+        digits.push(char::from(b'0' + (remainder / n128) as u8));
+        remainder %= n128;
+    }
+    if rest == 1 {
+        return (digits, 0);
+    }
+
+    digits.push('(');
+    let start = remainder;
+    let mut period = 0;
+    loop {
+        remainder *= 10;
+        digits.push(char::from(b'0' + (remainder / n128) as u8));
+        remainder %= n128;
+        period += 1;
+        if remainder == start {
+            break;
+        }
+    }
+    digits.push(')');
+    (digits, period)
+}
+
+// p/q by long division. The repetend is shown in parentheses when the
+// non-repeating prefix and one full period fit in `max_digits` places;
+// otherwise the expansion is cut there and marked with "...".
+fn rational_decimal(p: num_bigint::BigInt, q: num_bigint::BigInt, max_digits: u32) -> String {
+    use num_bigint::BigInt;
+    use num_integer::Integer;
+    use num_traits::{Signed, ToPrimitive, Zero};
+
+    assert!(!q.is_zero(), "rational with zero denominator");
+    let negative = p.is_negative() != q.is_negative() && !p.is_zero();
+    let (num, den) = reduce_fraction(p.abs(), q.abs());
+    let (whole, mut remainder) = num.div_rem(&den);
+    let mut out = if negative {
+        "-".to_string()
+    } else {
+        String::new()
+    };
+    out.push_str(&whole.to_string());
+    if remainder.is_zero() {
+        return out;
+    }
+
+    // As in reciprocal_decimal, the prefix length is the larger exponent of
+    // 2 or 5 in the reduced denominator.
+    let twos = den.trailing_zeros().unwrap_or(0);
+    let mut rest = &den >> twos;
+    let mut fives = 0;
+    while rest.is_multiple_of(&BigInt::from(5u32)) {
+        rest /= 5u32;
+        fives += 1;
+    }
+    let prefix_len = twos.max(fives);
+
+    if max_digits == 0 {
+        out.push_str("...");
+        return out;
+    }
+    out.push('.');
+    let next_digit = |remainder: &mut BigInt| {
+        *remainder *= 10u32;
+        let (digit, rem) = remainder.div_rem(&den);
+        *remainder = rem;
+        char::from(b'0' + digit.to_u8().unwrap_or(0))
+    };
+    let mut written = 0u64;
+    while written < prefix_len && written < max_digits as u64 {
+        out.push(next_digit(&mut remainder));
+        written += 1;
+    }
+    if remainder.is_zero() {
+        return out;
+    }
+    if written < prefix_len {
+        out.push_str("...");
+        return out;
+    }
+    let start = remainder.clone();
+    let mut repetend = String::new();
+    loop {
+        if written >= max_digits as u64 {
+            out.push_str(&repetend);
+            out.push_str("...");
+            return out;
+        }
+        repetend.push(next_digit(&mut remainder));
+        written += 1;
+        if remainder == start {
+            break;
+        }
+    }
+    out.push('(');
+    out.push_str(&repetend);
+    out.push(')');
+    out
+}
+
+fn collatz_steps(n: u64) -> u64 {
+    assert!(n > 0, "collatz of zero");
+    let mut x = n;
+    let mut steps = 0;
+    while x != 1 {
+        if x.is_multiple_of(2) {
+            x /= 2;
+        } else {
+            match x.checked_mul(3).and_then(|t| t.checked_add(1)) {
+                Some(next) => x = next,
+                // The peak of the trajectory no longer fits in u64.
+                None => return steps + collatz_steps_big(num_bigint::BigInt::from(x)),
+            }
+        }
+        steps += 1;
+    }
+    steps
+}
+
+fn collatz_steps_big(mut x: num_bigint::BigInt) -> u64 {
+    use num_integer::Integer;
+    use num_traits::One;
+
+    let mut steps = 0;
+    while !x.is_one() {
+        if x.is_even() {
+            x >>= 1u32;
+        } else {
+            x = x * 3u32 + 1u32;
+        }
+        steps += 1;
+    }
+    steps
+}
+
+// A binary PBM (P4) of the Ulam spiral with `side` cells a side: 1 sits at
+// the centre and the count winds outward anticlockwise, starting to the
+// right. Prime cells are black.
+fn ulam_spiral_pbm(side: usize) -> Vec<u8> {
+    let cells = side * side;
+    let mut composite = vec![false; cells + 1];
+    mark_composites(0, &sieve_primes(side), &mut composite);
+    let mut image = vec![false; cells];
+    let (mut x, mut y) = ((side as i64 - 1) / 2, side as i64 / 2);
+    let (mut dx, mut dy) = (1i64, 0i64);
+    let (mut run, mut taken, mut turns) = (1, 0, 0);
+    for (value, &is_composite) in composite.iter().enumerate().skip(1) {
+        if value >= 2 && !is_composite {
+            image[y as usize * side + x as usize] = true;
+        }
+        // Runs go 1, 1, 2, 2, 3, 3, ... with a left turn after each.
+        (x, y) = (x + dx, y + dy);
+        taken += 1;
+        if taken == run {
+            (dx, dy) = (dy, -dx);
+            taken = 0;
+            turns += 1;
+            if turns % 2 == 0 {
+                run += 1;
+            }
+        }
+    }
+    let mut pbm = format!("P4\n{side} {side}\n").into_bytes();
+    for row in image.chunks(side.max(1)) {
+        for bits in row.chunks(8) {
+            let byte = bits
+                .iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, &on)| acc | (u8::from(on) << (7 - i)));
+            pbm.push(byte);
+        }
+    }
+    pbm
+}
+
+// 2-automatic sequences: each term depends only on the binary digits of n.
+fn thue_morse(n: u64) -> u8 {
+    // Parity of the number of 1 bits.
+    (n.count_ones() % 2) as u8
+}
+
+fn paperfolding(n: u64) -> u8 {
+    // Folds are numbered from 1: writing n + 1 = k * 2^m with k odd, the
+    // fold is 1 when k = 1 (mod 4).
+    let k = (n + 1) >> (n + 1).trailing_zeros();
+    (k % 4 == 1) as u8
+}
+
+fn rudin_shapiro(n: u64) -> u8 {
+    // Parity of the number of adjacent "11" pairs.
+    ((n & (n >> 1)).count_ones() % 2) as u8
+}
+
+fn period_doubling(n: u64) -> u8 {
+    // Parity of how many times 2 divides n + 1.
+    ((n + 1).trailing_zeros() % 2) as u8
+}
+
+type SequenceTerm = fn(u64) -> u8;
+
+const AUTOMATIC_SEQUENCES: [(&str, SequenceTerm); 4] = [
+    ("thue-morse", thue_morse),
+    ("paperfolding", paperfolding),
+    ("rudin-shapiro", rudin_shapiro),
+    ("period-doubling", period_doubling),
+];
+
+fn farey_sequence(n: u64) -> Vec<(u64, u64)> {
+    if n == 0 {
+        return Vec::new();
+    }
+    // Each term follows from the previous two alone, so no mediant tree is
+    // built: with neighbours a/b < c/d, the next term is (kc - a)/(kd - b)
+    // for k = (n + b) / d.
+    let (mut a, mut b, mut c, mut d) = (0, 1, 1, n);
+    let mut terms = vec![(a, b)];
+    while c <= n {
+        terms.push((c, d));
+        let k = (n + b) / d;
+        (a, b, c, d) = (c, d, k * c - a, k * d - b);
+    }
+    terms
+}
+
+fn primitive_triples(max_c: u64) -> Vec<(u64, u64, u64)> {
+    // Euclid: for m > n > 0, coprime and of opposite parity, the triple
+    // (m² - n², 2mn, m² + n²) is primitive, and every primitive triple
+    // arises exactly once this way.
+    let mut triples = Vec::new();
+    let mut m = 2u64;
+    while m * m < max_c {
+        for n in (1 + m % 2..m).step_by(2) {
+            let c = m * m + n * n;
+            if c > max_c {
+                break;
+            }
+            if gcd_u64(m, n) == 1 {
+                let (a, b) = (m * m - n * n, 2 * m * n);
+                triples.push((a.min(b), a.max(b), c));
+            }
+        }
+        m += 1;
+    }
+    triples.sort_unstable_by_key(|&(a, b, c)| (c, a, b));
+    triples
+}
+
+fn stern_brocot_path(p: u64, q: u64) -> String {
+    assert!(p > 0 && q > 0, "Stern-Brocot fractions are positive");
+    let g = gcd_u64(p, q);
+    let (mut p, mut q) = (p / g, q / g);
+    // Descending to the mediant subtracts the smaller side from the larger;
+    // a run of equal moves is one division, as in the Euclidean algorithm.
+    // The last run stops one short, at 1/1 rather than at a zero.
+    let mut path = String::new();
+    while p != q {
+        if p < q {
+            let run = if q % p == 0 { q / p - 1 } else { q / p };
+            path.extend(std::iter::repeat_n('L', run as usize));
+            q -= run * p;
+        } else {
+            let run = if p % q == 0 { p / q - 1 } else { p / q };
+            path.extend(std::iter::repeat_n('R', run as usize));
+            p -= run * q;
+        }
+    }
+    path
+}
+
+fn continued_fraction(
+    num: &num_bigint::BigInt,
+    den: &num_bigint::BigInt,
+    max_terms: usize,
+) -> Vec<num_bigint::BigInt> {
+    use num_integer::Integer;
+    use num_traits::Zero;
+
+    let mut terms = Vec::new();
+    let (mut p, mut q) = (num.clone(), den.clone());
+    while !q.is_zero() && terms.len() < max_terms {
+        let (a, r) = p.div_mod_floor(&q);
+        terms.push(a);
+        p = q;
+        q = r;
+    }
+    terms
+}
+
+fn convergents(terms: &[num_bigint::BigInt]) -> Vec<(num_bigint::BigInt, num_bigint::BigInt)> {
+    use num_bigint::BigInt;
+    use num_traits::{One, Zero};
+
+    let (mut p_prev, mut p) = (BigInt::zero(), BigInt::one());
+    let (mut q_prev, mut q) = (BigInt::one(), BigInt::zero());
+    let mut out = Vec::with_capacity(terms.len());
+    for a in terms {
+        let p_next = a * &p + &p_prev;
+        let q_next = a * &q + &q_prev;
+        p_prev = std::mem::replace(&mut p, p_next);
+        q_prev = std::mem::replace(&mut q, q_next);
+        out.push((p.clone(), q.clone()));
+    }
+    out
+}
+
+fn ratio_to_f64(num: &num_bigint::BigInt, den: &num_bigint::BigInt) -> f64 {
+    use num_traits::ToPrimitive;
+
+    // Keep the top 60 bits of each side so huge or tiny ratios don't overflow.
+    let num_shift = num.bits().saturating_sub(60);
+    let den_shift = den.bits().saturating_sub(60);
+    let n = (num >> num_shift).to_f64().unwrap_or(0.0);
+    let d = (den >> den_shift).to_f64().unwrap_or(1.0);
+    n / d * 2f64.powi(num_shift as i32 - den_shift as i32)
+}
+
+fn decimal_to_fraction(text: &str) -> Option<(num_bigint::BigInt, num_bigint::BigInt)> {
+    let (whole, frac) = text.split_once('.').unwrap_or((text, ""));
+    let digits = format!("{whole}{frac}");
+    let num = digits.parse().ok()?;
+    Some((num, pow10(frac.len() as u32)))
+}
+
+struct Approximation {
+    num: num_bigint::BigInt,
+    den: num_bigint::BigInt,
+    error: f64,
+}
+
+// The first `count` convergents of num/den (den > 0), each with its exact
+// distance from num/den.
+fn convergent_approximations(
+    num: &num_bigint::BigInt,
+    den: &num_bigint::BigInt,
+    count: usize,
+) -> Vec<Approximation> {
+    use num_traits::Signed;
+
+    convergents(&continued_fraction(num, den, count))
+        .into_iter()
+        .map(|(p, q)| {
+            let diff = (&p * den - num * &q).abs();
+            let error = ratio_to_f64(&diff, &(&q * den));
+            Approximation {
+                num: p,
+                den: q,
+                error,
+            }
+        })
+        .collect()
+}
+
+// A decimal ("-3.14159") or a fraction ("355/113") as a reduced num/den with
+// den > 0.
+fn parse_rational(text: &str) -> Option<(num_bigint::BigInt, num_bigint::BigInt)> {
+    use num_traits::Zero;
+
+    let (num, den): (num_bigint::BigInt, num_bigint::BigInt) = match text.split_once('/') {
+        Some((p, q)) => (p.trim().parse().ok()?, q.trim().parse().ok()?),
+        None => decimal_to_fraction(text.trim())?,
+    };
+    if den.is_zero() {
+        return None;
+    }
+    Some(reduce_fraction(num, den))
+}
+
+fn pi_rational(count: usize, max_den: Option<u64>, digits: u32) -> Vec<Approximation> {
+    use num_bigint::BigInt;
+
+    let (pi_num, pi_den) = decimal_to_fraction(&compute_pi(digits)).expect("pi digits parse");
+    convergent_approximations(&pi_num, &pi_den, count)
+        .into_iter()
+        // Once q^2 nears 10^digits the rounding of pi, not the convergent,
+        // dominates the error, so later terms are no longer pi's own.
+        .take_while(|approx| {
+            let q = &approx.den;
+            q * q * 100u32 <= pi_den && max_den.is_none_or(|m| *q <= BigInt::from(m))
+        })
+        .collect()
+}
+
+const DEFAULT_SEED: u64 = 0x5eed;
+
+// SplitMix64: tiny, fast and well mixed, which is all benchmark inputs need.
+// Not suitable for anything cryptographic.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1), from the top 53 bits.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in [0, bound), by multiply-and-shift rather than a biased modulo.
+    fn below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+}
+
+struct MatmulOptions {
+    repeatable: bool,
+    threads: usize,
+    random_seed: Option<u64>,
+    thread_local: bool,
+    transpose_b: bool,
+    size: usize,
+    // (alpha, beta) for the full GEMM update C = alpha*A*B + beta*C.
+    gemm: Option<(f64, f64)>,
+    // Column block width of the register-blocked kernel, from --lanes.
+    lanes: Option<usize>,
+}
+
+impl Default for MatmulOptions {
+    fn default() -> Self {
+        MatmulOptions {
+            repeatable: false,
+            threads: 1,
+            random_seed: None,
+            thread_local: false,
+            transpose_b: false,
+            size: 128,
+            gemm: None,
+            lanes: None,
+        }
+    }
+}
+
+// Accumulates rows `first_row..` of alpha*A*B into `c_rows`, where A has
+// `inner` columns and B has `cols`. With alpha = 1 the scaling is exact, so
+// results match a plain A*B accumulation bit for bit.
+fn matmul_rows(
+    a: &[f64],
+    b: &[f64],
+    c_rows: &mut [f64],
+    first_row: usize,
+    inner: usize,
+    cols: usize,
+    alpha: f64,
+) {
+    for (r, c_row) in c_rows.chunks_mut(cols).enumerate() {
+        let i = first_row + r;
+        for k in 0..inner {
+            let aik = alpha * a[i * inner + k];
+            for j in 0..cols {
+                c_row[j] += aik * b[k * cols + j];
+            }
+        }
+    }
+}
+
+// The same product with B supplied transposed, so that the innermost loop
+// is a dot product reading both A and Bᵀ contiguously, and alpha scales the
+// finished dot product once per element. The terms a[i][k]*b[k][j] are summed
+// in increasing k, so with C cleared and alpha = 1 the result is
+// bit-identical to matmul_rows; otherwise the two differ only by rounding.
+fn matmul_rows_transposed(
+    a: &[f64],
+    bt: &[f64],
+    c_rows: &mut [f64],
+    first_row: usize,
+    inner: usize,
+    cols: usize,
+    alpha: f64,
+) {
+    for (r, c_row) in c_rows.chunks_mut(cols).enumerate() {
+        let a_row = &a[(first_row + r) * inner..][..inner];
+        for (j, cij) in c_row.iter_mut().enumerate() {
+            let bt_row = &bt[j * inner..][..inner];
+            let mut dot = 0.0;
+            for (aik, bkj) in a_row.iter().zip(bt_row) {
+                dot += aik * bkj;
+            }
+            *cij += alpha * dot;
+        }
+    }
+}
+
+type MatmulKernel = fn(&[f64], &[f64], &mut [f64], usize, usize, usize, f64);
+
+// --lanes widths and the CPU feature, as `cpuinfo` names it, that holds a
+// block of that many f64 in one (or for 16, two) vector registers.
+const MATMUL_LANES: [(usize, &str); 3] = [(4, "AVX"), (8, "AVX-512F"), (16, "AVX-512F")];
+
+// matmul_rows with each row of C walked in blocks of L columns, kept in a
+// local array for the whole k loop so the compiler can hold the block in
+// vector registers instead of reloading C for every k. Each element still
+// receives alpha*a[i][k]*b[k][j] one term at a time in increasing k, so the
+// result is bit-identical to matmul_rows for every L.
+#[inline(always)]
+fn matmul_rows_lanes<const L: usize>(
+    a: &[f64],
+    b: &[f64],
+    c_rows: &mut [f64],
+    first_row: usize,
+    inner: usize,
+    cols: usize,
+    alpha: f64,
+) {
+    for (r, c_row) in c_rows.chunks_mut(cols).enumerate() {
+        let i = first_row + r;
+        let a_row = &a[i * inner..(i + 1) * inner];
+        let mut blocks = c_row.chunks_exact_mut(L);
+        for (index, block) in (&mut blocks).enumerate() {
+            let j = index * L;
+            let mut acc = [0.0f64; L];
+            acc.copy_from_slice(block);
+            for (k, &a_ik) in a_row.iter().enumerate() {
+                let aik = alpha * a_ik;
+                let b_block = &b[k * cols + j..k * cols + j + L];
+                for (acc, &bkj) in acc.iter_mut().zip(b_block) {
+                    *acc += aik * bkj;
+                }
+            }
+            block.copy_from_slice(&acc);
+        }
+        let tail = blocks.into_remainder();
+        let j = cols - tail.len();
+        for (k, &a_ik) in a_row.iter().enumerate() {
+            let aik = alpha * a_ik;
+            for (cij, &bkj) in tail.iter_mut().zip(&b[k * cols + j..(k + 1) * cols]) {
+                *cij += aik * bkj;
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx")]
+fn matmul_rows_avx<const L: usize>(
+    a: &[f64],
+    b: &[f64],
+    c_rows: &mut [f64],
+    first_row: usize,
+    inner: usize,
+    cols: usize,
+    alpha: f64,
+) {
+    matmul_rows_lanes::<L>(a, b, c_rows, first_row, inner, cols, alpha);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+fn matmul_rows_avx512<const L: usize>(
+    a: &[f64],
+    b: &[f64],
+    c_rows: &mut [f64],
+    first_row: usize,
+    inner: usize,
+    cols: usize,
+    alpha: f64,
+) {
+    matmul_rows_lanes::<L>(a, b, c_rows, first_row, inner, cols, alpha);
+}
+
+// The default build targets baseline x86_64 (SSE2), so the wide kernels are
+// compiled with AVX or AVX-512 enabled and picked at run time. Detection is
+// cached by std, so checking per call costs an atomic load.
+fn matmul_rows_simd<const L: usize>(
+    a: &[f64],
+    b: &[f64],
+    c_rows: &mut [f64],
+    first_row: usize,
+    inner: usize,
+    cols: usize,
+    alpha: f64,
+) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if L >= 8 && std::arch::is_x86_feature_detected!("avx512f") {
+            // SAFETY: the CPU supports AVX-512F, checked just above.
+            return unsafe { matmul_rows_avx512::<L>(a, b, c_rows, first_row, inner, cols, alpha) };
+        }
+        if std::arch::is_x86_feature_detected!("avx") {
+            // SAFETY: the CPU supports AVX, checked just above.
+            return unsafe { matmul_rows_avx::<L>(a, b, c_rows, first_row, inner, cols, alpha) };
+        }
+    }
+    matmul_rows_lanes::<L>(a, b, c_rows, first_row, inner, cols, alpha);
+}
+
+fn matmul_lanes_kernel(lanes: usize) -> MatmulKernel {
+    match lanes {
+        4 => matmul_rows_simd::<4>,
+        8 => matmul_rows_simd::<8>,
+        16 => matmul_rows_simd::<16>,
+        _ => unreachable!("--lanes is validated against MATMUL_LANES"),
+    }
+}
+
+fn transpose(m: &[f64], rows: usize, cols: usize) -> Vec<f64> {
+    let mut t = vec![0.0; m.len()];
+    for (i, row) in m.chunks(cols).enumerate() {
+        for (j, &v) in row.iter().enumerate() {
+            t[j * rows + i] = v;
+        }
+    }
+    t
+}
+
+// Splits the rows of C across `threads` scoped threads and hands each
+// `work(rows, first_row)`.
+fn for_row_chunks(
+    c: &mut [f64],
+    cols: usize,
+    threads: usize,
+    work: impl Fn(&mut [f64], usize) + Sync,
+) {
+    let rows = c.len() / cols.max(1);
+    if threads <= 1 || rows == 0 {
+        work(c, 0);
+        return;
+    }
+    let rows_per_thread = rows.div_ceil(threads);
+    let work = &work;
+    std::thread::scope(|scope| {
+        for (t, chunk) in c.chunks_mut(rows_per_thread * cols).enumerate() {
+            scope.spawn(move || work(chunk, t * rows_per_thread));
+        }
+    });
+}
+
+// The same split for --thread-local, with workers that live for the whole
+// run. Each copies its rows of C into a buffer it allocates itself, then
+// runs `work` on that buffer once per call of the `step` handed to `drive`;
+// C is written back once, after `drive` returns. Linux places pages on the
+// node of the thread that first touches them, so on a NUMA machine every
+// worker accumulates into local memory instead of into one shared buffer
+// that lives wherever the main thread allocated it. The arithmetic per
+// element is unchanged, so results are bit-identical. `step` returns c[0],
+// which the caller cannot read directly while the workers own C.
+fn with_thread_local_rows<R>(
+    c: &mut [f64],
+    cols: usize,
+    threads: usize,
+    work: impl Fn(&mut [f64], usize) + Sync,
+    drive: impl FnOnce(&mut dyn FnMut() -> f64) -> R,
+) -> R {
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Barrier;
+
+    let rows = c.len() / cols.max(1);
+    let rows_per_thread = rows.div_ceil(threads.max(1)).max(1);
+    let chunks: Vec<&mut [f64]> = c.chunks_mut(rows_per_thread * cols.max(1)).collect();
+    // One party per worker plus the driving thread.
+    let start = Barrier::new(chunks.len() + 1);
+    let done = Barrier::new(chunks.len() + 1);
+    let stop = AtomicBool::new(false);
+    let first = AtomicU64::new(0);
+    let (work, start, done, stop, first) = (&work, &start, &done, &stop, &first);
+    std::thread::scope(|scope| {
+        for (t, chunk) in chunks.into_iter().enumerate() {
+            scope.spawn(move || {
+                let mut local = chunk.to_vec();
+                loop {
+                    start.wait();
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    work(&mut local, t * rows_per_thread);
+                    if let (0, Some(&c0)) = (t, local.first()) {
+                        first.store(c0.to_bits(), Ordering::Relaxed);
+                    }
+                    done.wait();
+                }
+                chunk.copy_from_slice(&local);
+            });
+        }
+        let result = drive(&mut || {
+            start.wait();
+            done.wait();
+            f64::from_bits(first.load(Ordering::Relaxed))
+        });
+        stop.store(true, Ordering::Relaxed);
+        start.wait();
+        result
+    })
+}
+
+// As in BLAS, beta = 0 overwrites C rather than scaling it, so stale Inf or
+// NaN values cannot leak through.
+fn prepare_c(c: &mut [f64], opts: &MatmulOptions) {
+    match opts.gemm {
+        Some((_, beta)) if beta != 0.0 => c.iter_mut().for_each(|x| *x *= beta),
+        Some(_) => c.fill(0.0),
+        // With random data C is cleared every pass as well: fed back through
+        // A without a reset it would grow geometrically and overflow to Inf
+        // on a long run.
+        None if opts.repeatable || opts.random_seed.is_some() => c.fill(0.0),
+        None => {}
+    }
+}
+
+fn matmul_threaded(a: &[f64], b: &[f64], c: &mut [f64], inner: usize, cols: usize, threads: usize) {
+    for_row_chunks(c, cols, threads, |rows, first_row| {
+        matmul_rows(a, b, rows, first_row, inner, cols, 1.0)
+    });
+}
+
+struct Matrix {
+    rows: usize,
+    cols: usize,
+    values: Vec<f64>,
+}
+
+// One row per line, entries separated by whitespace; blank lines are skipped.
+fn parse_matrix(text: &str) -> Result<Matrix, String> {
+    let mut values = Vec::new();
+    let mut rows = 0;
+    let mut cols = 0;
+    for (index, line) in text.lines().enumerate() {
+        let row: Vec<f64> = line
+            .split_whitespace()
+            .map(|token| {
+                token
+                    .parse()
+                    .map_err(|_| format!("line {}: '{token}' is not a number", index + 1))
+            })
+            .collect::<Result<_, _>>()?;
+        if row.is_empty() {
+            continue;
+        }
+        if rows == 0 {
+            cols = row.len();
+        } else if row.len() != cols {
+            return Err(format!(
+                "line {}: expected {cols} entries like the first row, found {}",
+                index + 1,
+                row.len()
+            ));
+        }
+        values.extend(row);
+        rows += 1;
+    }
+    if rows == 0 {
+        return Err("no rows found".to_string());
+    }
+    Ok(Matrix { rows, cols, values })
+}
+
+fn format_matrix(m: &Matrix) -> String {
+    m.values
+        .chunks(m.cols)
+        .map(|row| {
+            let cells: Vec<String> = row.iter().map(f64::to_string).collect();
+            cells.join(" ") + "\n"
+        })
+        .collect()
+}
+
+// Instruction sets relevant to the f64 kernels, detected at run time.
+// Empty on architectures without a detection macro.
+fn simd_features() -> Vec<(&'static str, bool)> {
+    #[cfg(target_arch = "x86_64")]
+    return vec![
+        ("SSE2", std::arch::is_x86_feature_detected!("sse2")),
+        ("AVX", std::arch::is_x86_feature_detected!("avx")),
+        ("AVX2", std::arch::is_x86_feature_detected!("avx2")),
+        ("FMA", std::arch::is_x86_feature_detected!("fma")),
+        ("AVX-512F", std::arch::is_x86_feature_detected!("avx512f")),
+    ];
+    #[cfg(target_arch = "aarch64")]
+    return vec![("NEON", std::arch::is_aarch64_feature_detected!("neon"))];
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    return Vec::new();
+}
+
+// Clock in GHz: the cpufreq maximum when the kernel exposes it, otherwise
+// the current "cpu MHz" of the first core in /proc/cpuinfo.
+fn cpu_ghz() -> Option<f64> {
+    if let Ok(text) =
+        std::fs::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/cpuinfo_max_freq")
+    {
+        if let Ok(khz) = text.trim().parse::<f64>() {
+            return Some(khz / 1.0e6);
+        }
+    }
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .find(|line| line.starts_with("cpu MHz"))
+        .and_then(|line| line.split(':').nth(1))
+        .and_then(|mhz| mhz.trim().parse::<f64>().ok())
+        .map(|mhz| mhz / 1.0e3)
+}
+
+// Double-precision peak for `threads` cores: clock × vector lanes × 2 for
+// FMA × 2 vector units per core. Returns the peak and how it was derived.
+fn estimate_peak_gflops(threads: usize) -> Option<(f64, String)> {
+    if !cfg!(target_arch = "x86_64") {
+        return None;
+    }
+    let features = simd_features();
+    let has = |name: &str| features.iter().any(|&(f, on)| f == name && on);
+    let lanes = if has("AVX-512F") {
+        8.0
+    } else if has("AVX") {
+        4.0
+    } else {
+        2.0
+    };
+    let fma = if has("FMA") { 2.0 } else { 1.0 };
+    let flop_per_cycle = lanes * fma * 2.0;
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let threads = threads.min(cores);
+    let ghz = cpu_ghz()?;
+    Some((
+        threads as f64 * ghz * flop_per_cycle,
+        format!(
+            "estimated: {threads} core{} × {ghz:.2} GHz × {flop_per_cycle} flop/cycle",
+            if threads == 1 { "" } else { "s" }
+        ),
+    ))
+}
+
+// Size in KiB of cpu0's data or unified cache at `level`, from sysfs.
+fn cache_size_kib(level: u32) -> Option<u64> {
+    let dir = std::fs::read_dir("/sys/devices/system/cpu/cpu0/cache").ok()?;
+    dir.flatten().find_map(|entry| {
+        let read = |file: &str| std::fs::read_to_string(entry.path().join(file)).ok();
+        let level_matches = read("level")?.trim().parse::<u32>().ok()? == level;
+        if !level_matches || read("type")?.trim() == "Instruction" {
+            return None;
+        }
+        let size = read("size")?;
+        let size = size.trim();
+        if let Some(kib) = size.strip_suffix('K') {
+            kib.parse().ok()
+        } else if let Some(mib) = size.strip_suffix('M') {
+            mib.parse::<u64>().ok().map(|mib| mib * 1024)
+        } else {
+            size.parse::<u64>().ok().map(|bytes| bytes / 1024)
+        }
+    })
+}
+
+// The largest n whose A, B and C (3 × n² doubles) fit in `kib`.
+fn matmul_size_for_cache(kib: u64) -> usize {
+    ((kib * 1024 / 24) as f64).sqrt().floor().max(1.0) as usize
+}
+
+fn checksum(values: &[f64]) -> u64 {
+    values.iter().fold(0xcbf2_9ce4_8422_2325, |acc, v| {
+        (acc ^ v.to_bits()).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn bench_matmul(sampler: &Sampler, opts: &MatmulOptions) -> BenchResult {
+    let n = opts.size;
+    let mut a = vec![1.001f64; n * n];
+    let mut b = vec![0.999f64; n * n];
+    if let Some(seed) = opts.random_seed {
+        // Centred on zero so the sums in C stay near sqrt(n) in size.
+        let mut rng = Rng::new(seed);
+        for x in a.iter_mut().chain(b.iter_mut()) {
+            *x = rng.next_f64() * 2.0 - 1.0;
+        }
+    }
+    let mut c = vec![0.0f64; n * n];
+    // GEMM adds n² multiplies for alpha (per a[i][k], or per dot product
+    // with B transposed) and n² for beta*C, unless beta = 0 only clears C.
+    let mut flop_per_iter = 2.0 * (n as f64).powi(3);
+    if let Some((_, beta)) = opts.gemm {
+        flop_per_iter += (n * n) as f64;
+        if beta != 0.0 {
+            flop_per_iter += (n * n) as f64;
+        }
+    }
+    let gflop_per_iter = flop_per_iter / 1.0e9;
+    let alpha = opts.gemm.map_or(1.0, |(alpha, _)| alpha);
+    // B never changes, so it is transposed once up front.
+    let bt = opts.transpose_b.then(|| transpose(&b, n, n));
+    let name = if opts.transpose_b {
+        "matmul_transposed"
+    } else {
+        "matmul"
+    };
+    let kernel: MatmulKernel = opts.lanes.map_or(matmul_rows, matmul_lanes_kernel);
+
+    // Each thread prepares and multiplies its own rows. A is only written
+    // between iterations, while no thread reads it.
+    let a = std::sync::RwLock::new(a);
+    let work = |rows: &mut [f64], first_row: usize| {
+        prepare_c(rows, opts);
+        let a = a.read().expect("A is never left poisoned");
+        match bt.as_deref() {
+            Some(bt) => matmul_rows_transposed(&a, bt, rows, first_row, n, n, alpha),
+            None => kernel(&a, &b, rows, first_row, n, n, alpha),
+        }
+        if opts.repeatable {
+            std::hint::black_box(rows);
+        }
+    };
+    let run = |step: &mut dyn FnMut() -> f64| {
+        sampler.run(name, "GFLOP/s", gflop_per_iter, || {
+            let c0 = step();
+            if !opts.repeatable {
+                // Not pi: the divisor only perturbs A, and changing it would
+                // shift every recorded matmul result.
+                #[allow(clippy::approx_constant)]
+                let divisor = 3.14159;
+                a.write().expect("A is never left poisoned")[0] = c0 / divisor;
+            }
+        })
+    };
+    let sampling = if opts.threads > 1 && opts.thread_local {
+        with_thread_local_rows(&mut c, n, opts.threads, work, run)
+    } else {
+        run(&mut || {
+            for_row_chunks(&mut c, n, opts.threads, work);
+            c[0]
+        })
+    };
+
+    let mut result = BenchResult::from_sampling(&sampling);
+    result.detail("Size", format!("{n}x{n}"));
+    if let Some((alpha, beta)) = opts.gemm {
+        result.detail("GEMM", format!("alpha {alpha}, beta {beta}"));
+    }
+    match opts.random_seed {
+        Some(seed) => result.detail("Data", format!("random (seed {seed})")),
+        None => result.detail("Data", "constant"),
+    }
+    if let Some(lanes) = opts.lanes {
+        result.detail("Lanes", format!("{lanes} x f64"));
+    }
+    if opts.threads > 1 {
+        result.detail("Threads", opts.threads);
+        result.detail(
+            "GFLOP/s per thread",
+            format!("{:.2}", result.overall / opts.threads as f64),
+        );
+        result.detail(
+            "Thread-local C",
+            if opts.thread_local { "yes" } else { "no" },
+        );
+    }
+    if opts.repeatable {
+        result.detail(
+            "Repeatable",
+            format!("yes (checksum {:016x})", checksum(&c)),
+        );
+    }
+    result
+}
+
+struct OperandSpec {
+    radix: u32,
+    addends: (u32, u32),
+    digits: u64,
+}
+
+impl Default for OperandSpec {
+    fn default() -> Self {
+        OperandSpec {
+            radix: 10,
+            addends: (7, 3),
+            digits: 4096,
+        }
+    }
+}
+
+// Bytes held per operand byte: both operands, their double-width product,
+// the accumulator, the temporary sum and the final decimal rendering.
+const OPERAND_MEMORY_FACTOR: f64 = 8.0;
+const DEFAULT_MAX_OPERAND_MEMORY_MIB: u64 = 1024;
+
+impl OperandSpec {
+    fn estimated_bytes(&self) -> f64 {
+        self.digits as f64 * (self.radix as f64).log2() / 8.0 * OPERAND_MEMORY_FACTOR
+    }
+
+    // Closed form of `digits` steps of x = x * radix + addend from x = 1:
+    // radix^digits + addend * (radix^digits - 1) / (radix - 1).
+    fn operand(&self, addend: u32) -> num_bigint::BigInt {
+        use num_bigint::BigInt;
+
+        let power = num_traits::pow(BigInt::from(self.radix), self.digits as usize);
+        let repunit = (&power - 1u32) / (self.radix - 1);
+        power + repunit * addend
+    }
+}
+
+fn bench_bigint(sampler: &Sampler, spec: &OperandSpec) -> BenchResult {
+    use num_bigint::BigInt;
+    use num_traits::One;
+
+    let a = spec.operand(spec.addends.0);
+    let b = spec.operand(spec.addends.1);
+    let operand_bits = format!("{} x {}", a.bits(), b.bits());
+    // Decimal length of the larger operand, from its bit length.
+    let operand_digits = (a.bits().max(b.bits()) as f64 * std::f64::consts::LOG10_2) as u64 + 1;
+
+    let mut acc = BigInt::one();
+    let sampling = sampler.run("bigint", "Multiplies/sec", 1.0, || {
+        acc = &a * &b + &acc;
+    });
+
+    let mut result = BenchResult::from_sampling(&sampling);
+    // Multiplies/sec falls as operands grow; scaling by the operand length
+    // puts runs of different sizes on one axis.
+    let digit_ops_per_sec = operand_digits as f64 * result.overall;
+    result.digit_ops_per_sec = Some(digit_ops_per_sec);
+    result.detail("Digit-ops/sec", format!("{digit_ops_per_sec:.0}"));
+    result.detail("Operand digits", operand_digits);
+    result.detail("Digits", acc.to_string().len());
+    result.detail("Operand bits", operand_bits);
+    result
+}
+
+fn bench_pi(
+    sampler: &Sampler,
+    suite: &'static str,
+    method: &str,
+    engine: PiEngine,
+    digits: u32,
+) -> BenchResult {
+    let mut last = String::new();
+    let sampling = sampler.run(suite, "Computations/sec", 1.0, || {
+        last = engine(digits);
+    });
+
+    let mut result = BenchResult::from_sampling(&sampling);
+    // Digits/sec is the figure pi records are quoted in.
+    let digits_per_sec = digits as f64 * result.overall;
+    result.digits_per_sec = Some(digits_per_sec);
+    result.detail("Digits/sec", format!("{digits_per_sec:.0}"));
+    result.detail("Digits", digits);
+    result.detail("Method", method);
+    std::hint::black_box(last);
+    result
+}
+
+fn bench_factorial(sampler: &Sampler, n: u64) -> BenchResult {
+    let mut last = factorial(0);
+    let sampling = sampler.run("factorial", "Factorials/sec", 1.0, || {
+        last = factorial(n);
+    });
+
+    let mut result = BenchResult::from_sampling(&sampling);
+    result.detail("N", n);
+    result.detail("Digits", last.to_string().len());
+    result
+}
+
+// Divisions per timed step, so the clock reads stay negligible.
+const DIV_BLOCK: u64 = 1 << 16;
+const DIV_DIVISOR: u64 = 0x1234_5679;
+// XORed into each quotient to keep the dependent chain's dividends large.
+const DIV_SALT: u64 = 0xd1b5_4a32_d192_ed03;
+
+fn bench_div(sampler: &Sampler, dependent: bool) -> BenchResult {
+    // Through black_box the divisor is unknown at compile time, so the
+    // divisions cannot be strength-reduced to multiplications.
+    let divisor = std::hint::black_box(DIV_DIVISOR);
+    let mut acc = 0u64;
+    let name = if dependent {
+        "div_dependent"
+    } else {
+        "div_independent"
+    };
+    let sampling = sampler.run(name, "Divisions/sec", DIV_BLOCK as f64, || {
+        if dependent {
+            // Each dividend comes from the previous quotient, so the
+            // divisions run back to back and the rate reflects latency.
+            let mut x = acc | DIV_SALT;
+            for _ in 0..DIV_BLOCK {
+                x = (x / divisor) ^ DIV_SALT;
+            }
+            acc = x;
+        } else {
+            // Dividends only depend on the loop counter, so the CPU can
+            // overlap as many divisions as its divider pipelines allow.
+            let base = acc | DIV_SALT;
+            let mut sum = 0u64;
+            for i in 0..DIV_BLOCK {
+                sum = sum.wrapping_add((base - i) / divisor);
+            }
+            acc = acc.wrapping_add(sum);
+        }
+        std::hint::black_box(acc);
+    });
+
+    let mut result = BenchResult::from_sampling(&sampling);
+    result.detail("Divisor", divisor);
+    if result.overall > 0.0 {
+        result.detail("Per division", format!("{:.2} ns", 1.0e9 / result.overall));
+    }
+    result
+}
+
+fn sieve_count(limit: usize) -> usize {
+    let mut sieve = vec![false; limit + 1];
+    let mut count = 0;
+    for i in 2..=limit {
+        if !sieve[i] {
+            count += 1;
+            let mut j = i * i;
+            while j <= limit {
+                sieve[j] = true;
+                j += i;
+            }
+        }
+    }
+    count
+}
+
+// 32 KiB of flags per window, so each thread's window stays in L1.
+const SIEVE_SEGMENT: usize = 1 << 15;
+
+fn sieve_segment_count(lo: usize, hi: usize, base: &[u64], window: &mut [bool]) -> usize {
+    let window = &mut window[..hi - lo];
+    mark_composites(lo, base, window);
+    let first = 2usize.saturating_sub(lo).min(window.len());
+    window[first..]
+        .iter()
+        .filter(|&&composite| !composite)
+        .count()
+}
+
+// Marks the composites in [lo, lo + window.len()); base must hold every prime
+// up to the square root of the window's end. 0 and 1 are left unmarked.
+fn mark_composites(lo: usize, base: &[u64], window: &mut [bool]) {
+    let hi = lo + window.len();
+    window.fill(false);
+    for &p in base {
+        let p = p as usize;
+        if p * p >= hi {
+            break;
+        }
+        let mut j = (p * p).max(lo.div_ceil(p) * p);
+        while j < hi {
+            window[j - lo] = true;
+            j += p;
+        }
+    }
+}
+
+fn sieve_count_segmented(limit: usize, threads: usize) -> usize {
+    let base = sieve_primes(isqrt_usize(limit));
+    let segments = (limit + 1).div_ceil(SIEVE_SEGMENT);
+    let threads = threads.clamp(1, segments.max(1));
+    let base = &base;
+    // Threads take every `threads`-th segment rather than one contiguous
+    // block, so the denser low segments are spread across all of them.
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|t| {
+                scope.spawn(move || {
+                    let mut window = vec![false; SIEVE_SEGMENT];
+                    (t..segments)
+                        .step_by(threads)
+                        .map(|segment| {
+                            let lo = segment * SIEVE_SEGMENT;
+                            let hi = (lo + SIEVE_SEGMENT).min(limit + 1);
+                            sieve_segment_count(lo, hi, base, &mut window)
+                        })
+                        .sum::<usize>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|w| w.join().expect("sieve worker panicked"))
+            .sum()
+    })
+}
+
+fn isqrt_usize(n: usize) -> usize {
+    let mut root = (n as f64).sqrt() as usize;
+    while root * root > n {
+        root -= 1;
+    }
+    while (root + 1) * (root + 1) <= n {
+        root += 1;
+    }
+    root
+}
+
+fn sieve_pass(limit: usize, threads: usize) -> usize {
+    if threads > 1 {
+        sieve_count_segmented(limit, threads)
+    } else {
+        sieve_count(limit)
+    }
+}
+
+// Larger than any last-level cache this is likely to meet, so writing one
+// byte per cache line evicts whatever the sieve left behind.
+const CACHE_BUST_BYTES: usize = 64 << 20;
+
+// Times sieve passes with the caches flushed before each one. Only the
+// passes themselves are timed, not the flushing between them.
+fn cold_sieve_rate(seconds: u64, limit: usize, threads: usize) -> (u64, f64) {
+    let mut buster = vec![0u8; CACHE_BUST_BYTES];
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    let mut timed = Duration::ZERO;
+    let mut passes: u64 = 0;
+    while Instant::now() < deadline && !interrupted() {
+        for line in buster.iter_mut().step_by(64) {
+            *line = line.wrapping_add(1);
+        }
+        std::hint::black_box(&mut buster);
+        let start = Instant::now();
+        std::hint::black_box(sieve_pass(limit, threads));
+        timed += start.elapsed();
+        passes += 1;
+    }
+    let rate = if timed.is_zero() {
+        0.0
+    } else {
+        passes as f64 / timed.as_secs_f64()
+    };
+    (passes, rate)
+}
+
+fn bench_sieve(sampler: &Sampler, threads: usize, cold: bool) -> BenchResult {
+    let limit = 2_000_000usize;
+    let mut primes_count: usize = sieve_pass(limit, threads);
+    let sampling = sampler.run("sieve", "Sieves/sec", 1.0, || {
+        primes_count = sieve_pass(limit, threads);
+    });
+
+    let mut result = BenchResult::from_sampling(&sampling);
+    result.detail("Limit", limit);
+    result.detail("Primes", primes_count);
+    if threads > 1 {
+        result.detail("Threads", threads);
+        result.detail(
+            "Sieves/sec per thread",
+            format!("{:.2}", result.overall / threads as f64),
+        );
+    }
+    if cold {
+        let (passes, rate) = cold_sieve_rate(sampler.seconds, limit, threads);
+        result.detail("Cold sieves/sec", format!("{rate:.2} ({passes} passes)"));
+        result.detail(
+            "Cold slowdown",
+            format!("{:.1}%", (1.0 - rate / result.overall) * 100.0),
+        );
+    }
+    result
+}
+
+const PAGE_SIZE: usize = 4096;
+
+#[cfg(target_os = "linux")]
+fn advise_hugepages(buf: &mut [f64]) -> bool {
+    extern "C" {
+        fn madvise(addr: *mut std::ffi::c_void, len: usize, advice: i32) -> i32;
+    }
+    const MADV_HUGEPAGE: i32 = 14;
+
+    // madvise wants a page-aligned start, so trim the range to whole pages.
+    let start = buf.as_mut_ptr() as usize;
+    let end = start + std::mem::size_of_val(buf);
+    let aligned = (start + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+    if aligned >= end {
+        return false;
+    }
+    let len = (end - aligned) & !(PAGE_SIZE - 1);
+    // SAFETY: start and end bound the live `&mut [f64]`, which stays borrowed
+    // for the whole call. The start is rounded up and the length rounded down
+    // to whole pages, so [aligned, aligned + len) lies inside [start, end) and
+    // never reaches memory outside the allocation. MADV_HUGEPAGE is only a
+    // hint and does not change the contents of the range.
+    unsafe { madvise(aligned as *mut std::ffi::c_void, len, MADV_HUGEPAGE) == 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn advise_hugepages(_buf: &mut [f64]) -> bool {
+    false
+}
+
+fn prefault(buf: &mut [f64], value: f64) {
+    let stride = PAGE_SIZE / std::mem::size_of::<f64>();
+    for i in (0..buf.len()).step_by(stride) {
+        buf[i] = value;
+    }
+}
+
+enum Access {
+    Sequential,
+    Stride(usize),
+    Random(u64),
+}
+
+struct MemoryOptions {
+    prefault: bool,
+    hugepages: bool,
+    access: Access,
+}
+
+impl Default for MemoryOptions {
+    fn default() -> Self {
+        MemoryOptions {
+            prefault: false,
+            hugepages: false,
+            access: Access::Sequential,
+        }
+    }
+}
+
+fn bench_memory(sampler: &Sampler, opts: &MemoryOptions) -> BenchResult {
+    let n = 1usize << 22;
+    // Zeroed allocations are mapped lazily, so by default the first passes of
+    // the triad loop pay for the page faults.
+    let mut a = vec![0.0f64; n];
+    let mut b = vec![0.0f64; n];
+    let mut c = vec![0.0f64; n];
+
+    let huge_status = if !opts.hugepages {
+        "off"
+    } else if [
+        advise_hugepages(&mut a),
+        advise_hugepages(&mut b),
+        advise_hugepages(&mut c),
+    ]
+    .iter()
+    .all(|&ok| ok)
+    {
+        "on"
+    } else {
+        "unavailable"
+    };
+    if opts.prefault {
+        prefault(&mut a, 0.5);
+        prefault(&mut b, 1.0);
+        prefault(&mut c, 2.0);
+    }
+
+    // Every pattern still touches each element exactly once per pass, so the
+    // bytes moved (and the GB/s reported) compare directly with sequential.
+    let order: Vec<u32> = match opts.access {
+        Access::Random(seed) => {
+            let mut rng = Rng::new(seed);
+            let mut order: Vec<u32> = (0..n as u32).collect();
+            for i in (1..n).rev() {
+                order.swap(i, rng.below(i as u64 + 1) as usize);
+            }
+            order
+        }
+        _ => Vec::new(),
+    };
+
+    let scalar = 3.0f64;
+    let gb_per_iter = (3 * n * std::mem::size_of::<f64>()) as f64 / 1.0e9;
+    let mut pass: u64 = 0;
+    let sampling = sampler.run("memory", "GB/s", gb_per_iter, || {
+        // Rotate the destination so every array is written; otherwise the
+        // read-only inputs would keep hitting the shared zero page.
+        let (dst, x, y) = match pass % 3 {
+            0 => (&mut a, &b, &c),
+            1 => (&mut b, &c, &a),
+            _ => (&mut c, &a, &b),
+        };
+        match opts.access {
+            Access::Sequential => {
+                for i in 0..n {
+                    dst[i] = x[i] + scalar * y[i];
+                }
+            }
+            Access::Stride(stride) => {
+                for start in 0..stride {
+                    for i in (start..n).step_by(stride) {
+                        dst[i] = x[i] + scalar * y[i];
+                    }
+                }
+            }
+            Access::Random(_) => {
+                for &i in &order {
+                    let i = i as usize;
+                    dst[i] = x[i] + scalar * y[i];
+                }
+            }
+        }
+        pass += 1;
+    });
+
+    let mut result = BenchResult::from_sampling(&sampling);
+    result.detail(
+        "Array",
+        format!(
+            "{n} x f64 ({} MiB each)",
+            (n * std::mem::size_of::<f64>()) >> 20
+        ),
+    );
+    result.detail("Prefault", if opts.prefault { "on" } else { "off" });
+    result.detail("Huge pages", huge_status);
+    match opts.access {
+        Access::Sequential => {}
+        Access::Stride(stride) => result.detail("Stride", format!("{stride} elements")),
+        Access::Random(seed) => result.detail("Access", format!("random (seed {seed})")),
+    }
+    result
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" {
+    fn signal(signum: i32, handler: usize) -> usize;
+}
+
+const SIGINT: i32 = 2;
+const SIG_DFL: usize = 0;
+
+extern "C" fn on_sigint(_signum: i32) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    // A second Ctrl-C should kill the process outright.
+    // SAFETY: signal is async-signal-safe, so it may be called from inside
+    // a handler, and SIG_DFL is a valid disposition for SIGINT.
+    unsafe {
+        signal(SIGINT, SIG_DFL);
+    }
+}
+
+fn install_interrupt_handler() {
+    // SAFETY: on_sigint is an extern "C" fn(i32) with the C handler
+    // signature and only touches an atomic and signal itself, both of which
+    // are async-signal-safe.
+    unsafe {
+        signal(SIGINT, on_sigint as extern "C" fn(i32) as usize);
+    }
+}
+
+// Points fd 2 at whatever fd 1 refers to, so errors, warnings, diagnostics
+// and panic messages all land in the stdout stream. Only where the text goes
+// changes; exit codes do not.
+#[cfg(unix)]
+fn route_stderr_to_stdout() -> bool {
+    extern "C" {
+        fn dup2(oldfd: i32, newfd: i32) -> i32;
+    }
+    // SAFETY: dup2 takes plain integers and touches no Rust-owned memory; at
+    // worst it fails with EBADF, reported through the -1 return.
+    unsafe { dup2(1, 2) != -1 }
+}
+
+#[cfg(not(unix))]
+fn route_stderr_to_stdout() -> bool {
+    false
+}
+
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+struct Sampler {
+    seconds: u64,
+    latency: bool,
+    stream: bool,
+    trace: bool,
+}
+
+// Iterations kept for --trace (80 MB of durations); later ones are counted
+// but not recorded.
+const TRACE_MAX_ITERATIONS: usize = 10_000_000;
+
+struct Sampling {
+    name: &'static str,
+    unit: &'static str,
+    iterations: u64,
+    elapsed: f64,
+    work_per_iter: f64,
+    samples: Vec<f64>,
+    latencies: Vec<f64>,
+    trace: Vec<u64>,
+}
+
+impl Sampler {
+    // Runs `step` until the time budget is spent, recording throughput (in
+    // units of `work_per_iter` per second) for every one-second window.
+    fn run(
+        &self,
+        name: &'static str,
+        unit: &'static str,
+        work_per_iter: f64,
+        mut step: impl FnMut(),
+    ) -> Sampling {
+        let start = Instant::now();
+        let duration = Duration::from_secs(self.seconds);
+        let sample_window = Duration::from_secs(1);
+        let mut iterations: u64 = 0;
+        let mut samples: Vec<f64> = Vec::new();
+        let mut latencies: Vec<f64> = Vec::new();
+        let mut trace: Vec<u64> = Vec::new();
+
+        while start.elapsed() < duration && !interrupted() {
+            let sample_start = Instant::now();
+            let mut sample_iters: u64 = 0;
+            while sample_start.elapsed() < sample_window
+                && start.elapsed() < duration
+                && !interrupted()
+            {
+                if self.latency || self.trace {
+                    let iter_start = Instant::now();
+                    step();
+                    let nanos = iter_start.elapsed().as_nanos() as u64;
+                    if self.latency {
+                        latencies.push(nanos as f64);
+                    }
+                    if self.trace && trace.len() < TRACE_MAX_ITERATIONS {
+                        trace.push(nanos);
+                    }
+                } else {
+                    step();
+                }
+                iterations += 1;
+                sample_iters += 1;
+            }
+            let sample_elapsed = sample_start.elapsed().as_secs_f64();
+            if sample_elapsed > 0.0 {
+                let rate = work_per_iter * sample_iters as f64 / sample_elapsed;
+                samples.push(rate);
+                if self.stream {
+                    stream_sample(name, unit, start.elapsed().as_secs_f64(), rate);
+                }
+            }
+        }
+
+        Sampling {
+            name,
+            unit,
+            iterations,
+            elapsed: start.elapsed().as_secs_f64(),
+            work_per_iter,
+            samples,
+            latencies,
+            trace,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct LatencyStats {
+    min: f64,
+    median: f64,
+    p99: f64,
+    max: f64,
+}
+
+impl LatencyStats {
+    fn from_nanos(latencies: &[f64]) -> Option<Self> {
+        if latencies.is_empty() {
+            return None;
+        }
+        let mut sorted = latencies.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        Some(LatencyStats {
+            min: sorted[0],
+            median: percentile(&sorted, 50.0),
+            p99: percentile(&sorted, 99.0),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (pct / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+fn format_nanos(nanos: f64) -> String {
+    if nanos >= 1.0e6 {
+        format!("{:.3} ms", nanos / 1.0e6)
+    } else if nanos >= 1.0e3 {
+        format!("{:.3} us", nanos / 1.0e3)
+    } else {
+        format!("{nanos:.0} ns")
+    }
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct BenchResult {
+    #[serde(rename = "suite")]
+    name: String,
+    unit: String,
+    iterations: u64,
+    min: f64,
+    avg: f64,
+    max: f64,
+    overall: f64,
+    latency: Option<LatencyStats>,
+    throttle: Option<f64>,
+    cv: Option<f64>,
+    // pi suites only: digits computed per second, the headline figure.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digits_per_sec: Option<f64>,
+    // bigint only: multiplies/sec times the operand digits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digit_ops_per_sec: Option<f64>,
+    details: Vec<(String, String)>,
+    // Per-iteration nanoseconds, only with --trace, which writes them out.
+    #[serde(skip)]
+    trace: Vec<u64>,
+}
+
+// Stamped on every JSON object the helper writes; bumped whenever a field is
+// renamed, removed or changes meaning.
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+// One NDJSON line per sample window, e.g.
+// {"schema_version":1,"suite":"matmul","t":3.0,"gflops":41.2}, where t is
+// seconds into the run.
+fn stream_sample(name: &str, unit: &str, t: f64, rate: f64) {
+    println!("{}", stream_sample_line(name, unit, t, rate));
+}
+
+fn stream_sample_line(name: &str, unit: &str, t: f64, rate: f64) -> String {
+    // Built by hand because serde_json::Map would sort the keys.
+    format!(
+        "{{\"schema_version\":{JSON_SCHEMA_VERSION},\"suite\":{},\"t\":{},\"{}\":{}}}",
+        serde_json::Value::from(name),
+        serde_json::Value::from((t * 1000.0).round() / 1000.0),
+        metric_suffix(unit),
+        serde_json::Value::from(rate)
+    )
+}
+
+impl BenchResult {
+    fn from_sampling(sampling: &Sampling) -> Self {
+        let (min, avg, max) = stats(&sampling.samples);
+        let overall = if sampling.elapsed > 0.0 {
+            sampling.work_per_iter * sampling.iterations as f64 / sampling.elapsed
+        } else {
+            0.0
+        };
+        BenchResult {
+            name: sampling.name.to_string(),
+            unit: sampling.unit.to_string(),
+            iterations: sampling.iterations,
+            min,
+            avg,
+            max,
+            overall,
+            latency: LatencyStats::from_nanos(&sampling.latencies),
+            throttle: throttle_drop(&sampling.samples),
+            cv: coefficient_of_variation(&sampling.samples),
+            digits_per_sec: None,
+            digit_ops_per_sec: None,
+            details: Vec::new(),
+            trace: sampling.trace.clone(),
+        }
+    }
+
+    fn detail(&mut self, label: &'static str, value: impl fmt::Display) {
+        self.details.push((label.to_string(), value.to_string()));
+    }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = &self.unit;
+        if let Some(latency) = &self.latency {
+            write!(
+                f,
+                "Iterations: {}\nLatency min: {}\nLatency median: {}\nLatency p99: {}\nLatency max: {}",
+                self.iterations,
+                format_nanos(latency.min),
+                format_nanos(latency.median),
+                format_nanos(latency.p99),
+                format_nanos(latency.max)
+            )?;
+        } else {
+            write!(
+                f,
+                "Iterations: {}\n{unit} avg: {:.2}\n{unit} min: {:.2}\n{unit} max: {:.2}\n{unit} overall: {:.2}",
+                self.iterations, self.avg, self.min, self.max, self.overall
+            )?;
+        }
+        for (label, value) in &self.details {
+            write!(f, "\n{label}: {value}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Tsv,
+    Prometheus,
+    Json,
+    MessagePack,
+}
+
+struct BenchOptions {
+    warmup: u64,
+    best_of: u32,
+    latency: bool,
+    throttle_detect: bool,
+    energy: bool,
+    format: OutputFormat,
+    output: Option<String>,
+    history: Option<String>,
+    max_duration: u64,
+    assume_yes: bool,
+    // In percent, as given on the command line.
+    max_cv: Option<f64>,
+    retries: u32,
+    sysinfo: bool,
+    stream_samples: bool,
+    trace: Option<String>,
+    parameters: Vec<String>,
+}
+
+impl BenchOptions {
+    fn from_args(args: &mut Vec<String>) -> Self {
+        let parameters = args.clone();
+        let tsv = take_flag(args, "--tsv");
+        let prometheus = take_flag(args, "--prometheus");
+        let format = take_option(args, "--format").map(|name| match name.as_str() {
+            "text" => OutputFormat::Text,
+            "tsv" => OutputFormat::Tsv,
+            "prometheus" => OutputFormat::Prometheus,
+            "json" => OutputFormat::Json,
+            "msgpack" => OutputFormat::MessagePack,
+            _ => {
+                eprintln!("--format must be text, tsv, prometheus, json or msgpack, got '{name}'");
+                std::process::exit(EXIT_USAGE);
+            }
+        });
+        let opts = BenchOptions {
+            warmup: option_or(args, "--warmup", 0),
+            best_of: option_or(args, "--best-of", 1u32).max(1),
+            latency: take_flag(args, "--latency"),
+            throttle_detect: take_flag(args, "--thermal-throttle-detect"),
+            energy: take_flag(args, "--energy"),
+            format: if let Some(format) = format {
+                format
+            } else if prometheus {
+                OutputFormat::Prometheus
+            } else if tsv {
+                OutputFormat::Tsv
+            } else {
+                OutputFormat::Text
+            },
+            output: take_option(args, "--output"),
+            history: take_option(args, "--history"),
+            max_duration: option_or(args, "--max-duration", DEFAULT_MAX_DURATION),
+            assume_yes: take_flag(args, "--yes") | take_flag(args, "--force"),
+            max_cv: take_option(args, "--max-cv").map(|text| match text.parse() {
+                Ok(percent) if f64::is_finite(percent) && percent > 0.0 => percent,
+                _ => {
+                    eprintln!("--max-cv needs a positive percentage, got '{text}'");
+                    std::process::exit(EXIT_USAGE);
+                }
+            }),
+            retries: option_or(args, "--retries", 3),
+            sysinfo: take_flag(args, "--sysinfo"),
+            stream_samples: take_flag(args, "--stream-samples"),
+            trace: take_option(args, "--trace"),
+            parameters,
+        };
+        if opts.format == OutputFormat::MessagePack && opts.output.is_none() {
+            use std::io::IsTerminal;
+            if opts.stream_samples {
+                eprintln!("--format msgpack with --stream-samples needs --output <file>");
+                std::process::exit(EXIT_USAGE);
+            }
+            if std::io::stdout().is_terminal() {
+                eprintln!("warning: writing binary MessagePack to a terminal; use --output <file>");
+            }
+        }
+        if opts.trace.is_some() {
+            eprintln!(
+                "warning: --trace times every iteration, which adds overhead; \
+                 it is only meaningful for coarse iterations such as whole sieves"
+            );
+        }
+        opts
+    }
+}
+
+const RAPL_DIR: &str = "/sys/class/powercap/intel-rapl:0";
+
+fn read_rapl(file: &str) -> std::io::Result<u64> {
+    let text = std::fs::read_to_string(format!("{RAPL_DIR}/{file}"))?;
+    text.trim()
+        .parse()
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+// Runs one measurement and, when the RAPL package counter is readable,
+// attaches the energy it used. The counter covers the whole CPU package, so
+// anything else running at the same time is billed to the benchmark too.
+fn run_with_energy(sampler: &Sampler, run: &impl Fn(&Sampler) -> BenchResult) -> BenchResult {
+    let before = read_rapl("energy_uj");
+    let start = Instant::now();
+    let mut result = run(sampler);
+    let seconds = start.elapsed().as_secs_f64();
+    let (Ok(before), Ok(after)) = (before, read_rapl("energy_uj")) else {
+        return result;
+    };
+    // The counter wraps at max_energy_range_uj.
+    let used_uj = if after >= before {
+        after - before
+    } else {
+        read_rapl("max_energy_range_uj").unwrap_or(u64::MAX) - before + after
+    };
+    let joules = used_uj as f64 / 1e6;
+    if seconds > 0.0 && joules > 0.0 {
+        let watts = joules / seconds;
+        result.detail("Energy", format!("{joules:.1} J"));
+        result.detail("Power", format!("{watts:.1} W"));
+        result.detail(
+            "Per watt",
+            format!("{:.3} {} per W", result.overall / watts, result.unit),
+        );
+    }
+    result
+}
+
+// Runs longer than this (warmup included) need --yes or a confirmation.
+const DEFAULT_MAX_DURATION: u64 = 3600;
+
+// The worst-case wall time of one run_bench call, counting every retry
+// --max-cv may spend.
+fn bench_duration(seconds: u64, opts: &BenchOptions) -> u64 {
+    let retries = if opts.max_cv.is_some() {
+        opts.retries as u64
+    } else {
+        0
+    };
+    let runs = (opts.best_of as u64).saturating_add(retries);
+    opts.warmup.saturating_add(seconds.saturating_mul(runs))
+}
+
+// Guards against typos such as `bench-matmul 6000000`. Each command checks
+// the total of all its suites once, before running any of them.
+fn confirm_duration(total: u64, opts: &BenchOptions) {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if total <= opts.max_duration {
+        return;
+    }
+    let limit = opts.max_duration;
+    if opts.assume_yes {
+        eprintln!("warning: running for {total}s, over the {limit}s limit");
+    } else if std::io::stdin().is_terminal() {
+        eprint!("This run takes {total}s, over the {limit}s limit. Continue? [y/N] ");
+        let _ = std::io::stderr().flush();
+        let mut answer = String::new();
+        let _ = std::io::stdin().lock().read_line(&mut answer);
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            eprintln!("aborted");
+            std::process::exit(EXIT_USAGE);
+        }
+    } else {
+        eprintln!(
+            "refusing to run for {total}s, over the {limit}s limit: pass --yes, or raise --max-duration"
+        );
+        std::process::exit(EXIT_USAGE);
+    }
+}
+
+fn run_bench(
+    seconds: u64,
+    opts: &BenchOptions,
+    run: impl Fn(&Sampler) -> BenchResult,
+) -> BenchResult {
+    install_interrupt_handler();
+    let energy = opts.energy
+        && match read_rapl("energy_uj") {
+            Ok(_) => true,
+            Err(err) => {
+                eprintln!("warning: --energy ignored, cannot read {RAPL_DIR}/energy_uj: {err}");
+                false
+            }
+        };
+    let run = |sampler: &Sampler| {
+        if energy {
+            run_with_energy(sampler, &run)
+        } else {
+            run(sampler)
+        }
+    };
+    if opts.warmup > 0 {
+        run(&Sampler {
+            seconds: opts.warmup,
+            latency: false,
+            stream: false,
+            trace: false,
+        });
+    }
+    let sampler = Sampler {
+        seconds,
+        latency: opts.latency,
+        stream: opts.stream_samples,
+        trace: opts.trace.is_some(),
+    };
+    // With --max-cv, a run noisier than the limit is repeated, sharing one
+    // budget of retries across all --best-of runs, and the steadiest attempt
+    // stands in for it.
+    let mut retries_left = opts.retries;
+    let mut retried = 0;
+    let mut run = |sampler: &Sampler| {
+        let mut result = run(sampler);
+        let Some(limit) = opts.max_cv else {
+            return result;
+        };
+        while retries_left > 0 && !interrupted() && result.cv.is_some_and(|cv| cv * 100.0 > limit) {
+            verbose!(
+                1,
+                "CV {:.2}% over the {limit}% limit, retrying",
+                result.cv.unwrap_or(0.0) * 100.0
+            );
+            retries_left -= 1;
+            retried += 1;
+            let again = run(sampler);
+            if again.cv.unwrap_or(0.0) < result.cv.unwrap_or(0.0) {
+                result = again;
+            }
+        }
+        result
+    };
+    let mut best = run(&sampler);
+    let mut best_index = 1;
+    verbose!(1, "run 1: {:.2} {} overall", best.overall, best.unit);
+    for index in 2..=opts.best_of {
+        if interrupted() {
+            break;
+        }
+        let result = run(&sampler);
+        verbose!(
+            1,
+            "run {index}: {:.2} {} overall",
+            result.overall,
+            result.unit
+        );
+        if result.overall > best.overall {
+            best = result;
+            best_index = index;
+        }
+    }
+    if opts.warmup > 0 {
+        best.detail("Warmup", format!("{}s", opts.warmup));
+    }
+    if opts.best_of > 1 {
+        best.detail("Best of", format!("{} (run {best_index})", opts.best_of));
+    }
+    if let Some(limit) = opts.max_cv {
+        if let Some(cv) = best.cv {
+            best.detail("CV", format!("{:.2}% (limit {limit}%)", cv * 100.0));
+        }
+        best.detail("Retries", retried);
+    }
+    if opts.throttle_detect {
+        if let Some(drop) = best.throttle {
+            best.detail(
+                "Throttling detected",
+                format!("~{:.0}% drop from start to end", drop * 100.0),
+            );
+        }
+    }
+    if interrupted() {
+        best.detail("Partial", "yes (interrupted)");
+    }
+    best
+}
+
+fn render_table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|col| {
+            rows.iter()
+                .filter_map(|row| row.get(col))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(col, cell)| {
+                    if col + 1 == row.len() {
+                        cell.clone()
+                    } else {
+                        format!("{cell:<width$}", width = widths[col])
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\t")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn bench_table(results: &[BenchResult]) -> String {
+    let mut rows = vec![[
+        "Benchmark",
+        "Unit",
+        "Iterations",
+        "Avg",
+        "Min",
+        "Max",
+        "Overall",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect::<Vec<_>>()];
+    for r in results {
+        rows.push(vec![
+            r.name.to_string(),
+            r.unit.to_string(),
+            r.iterations.to_string(),
+            format!("{:.2}", r.avg),
+            format!("{:.2}", r.min),
+            format!("{:.2}", r.max),
+            format!("{:.2}", r.overall),
+        ]);
+    }
+    render_table(&rows)
+}
+
+fn metric_suffix(unit: &str) -> String {
+    match unit {
+        "GFLOP/s" => "gflops".to_string(),
+        "GB/s" => "gigabytes_per_second".to_string(),
+        _ => unit
+            .to_lowercase()
+            .replace("/sec", "_per_second")
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "_"),
+    }
+}
+
+fn prometheus_metrics(results: &[BenchResult]) -> String {
+    let mut out = String::new();
+    for r in results {
+        let metric = format!("gensuite_{}_{}", r.name, metric_suffix(&r.unit));
+        out.push_str(&format!(
+            "# HELP {metric} {} benchmark throughput in {}.\n# TYPE {metric} gauge\n",
+            r.name, r.unit
+        ));
+        for (stat, value) in [
+            ("avg", r.avg),
+            ("min", r.min),
+            ("max", r.max),
+            ("overall", r.overall),
+        ] {
+            out.push_str(&format!("{metric}{{stat=\"{stat}\"}} {value}\n"));
+        }
+        if let Some(latency) = &r.latency {
+            let metric = format!("gensuite_{}_latency_seconds", r.name);
+            out.push_str(&format!(
+                "# HELP {metric} Per-iteration latency of the {} benchmark.\n# TYPE {metric} gauge\n",
+                r.name
+            ));
+            for (quantile, nanos) in [
+                ("0", latency.min),
+                ("0.5", latency.median),
+                ("0.99", latency.p99),
+                ("1", latency.max),
+            ] {
+                out.push_str(&format!(
+                    "{metric}{{quantile=\"{quantile}\"}} {}\n",
+                    nanos / 1.0e9
+                ));
+            }
+        }
+        let iterations = format!("gensuite_{}_iterations", r.name);
+        out.push_str(&format!(
+            "# HELP {iterations} Iterations completed by the {} benchmark run.\n# TYPE {iterations} gauge\n{iterations} {}\n",
+            r.name, r.iterations
+        ));
+    }
+    out
+}
+
+// The whole run as one JSON object or MessagePack map: {schema_version,
+// system (with --sysinfo), results: [...]}.
+#[derive(serde::Serialize)]
+struct BenchReport<'a> {
+    schema_version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<SystemInfo>,
+    results: &'a [BenchResult],
+}
+
+// A computed constant from `pi` or `e` with --format json or msgpack.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct ConstantReport {
+    schema_version: u32,
+    constant: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    method: Option<String>,
+    digits: u32,
+    value: String,
+}
+
+fn take_constant_format(args: &mut Vec<String>) -> OutputFormat {
+    match take_option(args, "--format").as_deref() {
+        None | Some("text") => OutputFormat::Text,
+        Some("json") => OutputFormat::Json,
+        Some("msgpack") => {
+            use std::io::IsTerminal;
+            if std::io::stdout().is_terminal() {
+                eprintln!(
+                    "warning: writing binary MessagePack to a terminal; redirect stdout to a file"
+                );
+            }
+            OutputFormat::MessagePack
+        }
+        Some(name) => {
+            eprintln!("--format must be text, json or msgpack, got '{name}'");
+            std::process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+fn render_constant(report: &ConstantReport, format: OutputFormat) -> Vec<u8> {
+    match format {
+        OutputFormat::Json => {
+            let json = serde_json::to_string(report).unwrap_or_else(|err| {
+                eprintln!("could not encode the result as JSON: {err}");
+                std::process::exit(EXIT_FAILURE);
+            });
+            format!("{json}\n").into_bytes()
+        }
+        OutputFormat::MessagePack => msgpack::to_vec(report).unwrap_or_else(|err| {
+            eprintln!("could not encode the result as MessagePack: {err}");
+            std::process::exit(EXIT_FAILURE);
+        }),
+        _ => format!("{}\n", report.value).into_bytes(),
+    }
+}
+
+fn print_constant(report: &ConstantReport, format: OutputFormat) {
+    use std::io::Write;
+    if let Err(err) = std::io::stdout().write_all(&render_constant(report, format)) {
+        eprintln!("could not write the result: {err}");
+        std::process::exit(EXIT_FAILURE);
+    }
+}
+
+fn render_results(results: &[BenchResult], format: OutputFormat, sysinfo: bool) -> Vec<u8> {
+    let system = sysinfo.then(SystemInfo::current);
+    let mut text = match format {
+        OutputFormat::Json => {
+            let report = BenchReport {
+                schema_version: JSON_SCHEMA_VERSION,
+                system,
+                results,
+            };
+            let json = serde_json::to_string(&report).unwrap_or_else(|err| {
+                eprintln!("could not encode the results as JSON: {err}");
+                std::process::exit(EXIT_FAILURE);
+            });
+            return format!("{json}\n").into_bytes();
+        }
+        OutputFormat::MessagePack => {
+            let report = BenchReport {
+                schema_version: JSON_SCHEMA_VERSION,
+                system,
+                results,
+            };
+            return msgpack::to_vec(&report).unwrap_or_else(|err| {
+                eprintln!("could not encode the results as MessagePack: {err}");
+                std::process::exit(EXIT_FAILURE);
+            });
+        }
+        OutputFormat::Tsv => format!("{}\n", bench_table(results)),
+        OutputFormat::Prometheus => prometheus_metrics(results),
+        OutputFormat::Text => match results {
+            [single] => format!("{single}\n"),
+            _ => {
+                let blocks: Vec<String> = results
+                    .iter()
+                    .map(|r| format!("[{}]\n{r}", r.name))
+                    .collect();
+                format!("{}\n", blocks.join("\n\n"))
+            }
+        },
+    };
+    if let Some(system) = system {
+        text.insert_str(0, &system.header(format));
+    }
+    text.into_bytes()
+}
+
+// Writes to a sibling temp file and renames it into place, so a textfile
+// collector scraping the directory never sees a half-written file.
+fn write_atomically(path: &str, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let target = std::path::Path::new(path);
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp = target.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, target).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SystemInfo {
+    cores: usize,
+    os: String,
+    arch: String,
+    profile: String,
+}
+
+impl SystemInfo {
+    fn current() -> Self {
+        SystemInfo {
+            cores: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            }
+            .to_string(),
+        }
+    }
+
+    // TSV and Prometheus readers skip '#' lines, so the header is emitted as
+    // comments there and as plain detail lines in text output.
+    fn header(&self, format: OutputFormat) -> String {
+        let prefix = match format {
+            OutputFormat::Text => "",
+            OutputFormat::Tsv
+            | OutputFormat::Prometheus
+            | OutputFormat::Json
+            | OutputFormat::MessagePack => "# ",
+        };
+        let mut out = String::new();
+        for (label, value) in [
+            ("Cores", self.cores.to_string()),
+            ("OS", self.os.clone()),
+            ("Arch", self.arch.clone()),
+            ("Profile", self.profile.clone()),
+        ] {
+            out.push_str(&format!("{prefix}{label}: {value}\n"));
+        }
+        if format == OutputFormat::Text {
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct HistoryEntry {
+    // 0 for entries written before the field existed.
+    #[serde(default)]
+    schema_version: u32,
+    timestamp: u64,
+    version: String,
+    suite: String,
+    unit: String,
+    parameters: Vec<String>,
+    iterations: u64,
+    min: f64,
+    avg: f64,
+    max: f64,
+    overall: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    digits_per_sec: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    digit_ops_per_sec: Option<f64>,
+    details: std::collections::BTreeMap<String, String>,
+    // Absent from entries written before system info was recorded.
+    #[serde(default)]
+    system: Option<SystemInfo>,
+}
+
+impl HistoryEntry {
+    fn new(result: &BenchResult, parameters: &[String], timestamp: u64) -> Self {
+        HistoryEntry {
+            schema_version: JSON_SCHEMA_VERSION,
+            timestamp,
+            version: format!(
+                "{} ({})",
+                env!("CARGO_PKG_VERSION"),
+                env!("GENSUITE_GIT_REV")
+            ),
+            suite: result.name.to_string(),
+            unit: result.unit.to_string(),
+            parameters: parameters.to_vec(),
+            iterations: result.iterations,
+            min: result.min,
+            avg: result.avg,
+            max: result.max,
+            overall: result.overall,
+            digits_per_sec: result.digits_per_sec,
+            digit_ops_per_sec: result.digit_ops_per_sec,
+            details: result
+                .details
+                .iter()
+                .map(|(label, value)| (label.to_string(), value.clone()))
+                .collect(),
+            system: Some(SystemInfo::current()),
+        }
+    }
+}
+
+// Appends one JSON line per result. All lines go out in a single write under
+// an exclusive lock on an O_APPEND handle, so concurrent runs cannot
+// interleave or overwrite each other's entries.
+fn append_history(
+    path: &str,
+    results: &[BenchResult],
+    parameters: &[String],
+) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let mut lines = String::new();
+    for result in results {
+        let entry = HistoryEntry::new(result, parameters, timestamp);
+        lines.push_str(&serde_json::to_string(&entry).map_err(std::io::Error::other)?);
+        lines.push('\n');
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.lock()?;
+    file.write_all(lines.as_bytes())
+}
+
+fn history_summary(path: &str) -> Result<String, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|err| format!("could not read {path}: {err}"))?;
+    // Suites keep the order they first appear in.
+    let mut suites: Vec<(String, String, Vec<f64>)> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: HistoryEntry = serde_json::from_str(line)
+            .map_err(|err| format!("{path}: line {}: {err}", index + 1))?;
+        match suites
+            .iter_mut()
+            .find(|(suite, unit, _)| *suite == entry.suite && *unit == entry.unit)
+        {
+            Some((_, _, values)) => values.push(entry.overall),
+            None => suites.push((entry.suite, entry.unit, vec![entry.overall])),
+        }
+    }
+    let mut out = String::new();
+    for (suite, unit, values) in &suites {
+        let (min, avg, max) = stats(values);
+        out.push_str(&format!(
+            "{suite} ({unit}): {} runs, min {min:.2}, avg {avg:.2}, max {max:.2}, latest {:.2}, trend {}\n",
+            values.len(),
+            values[values.len() - 1],
+            format_trend(values, avg)
+        ));
+    }
+    Ok(out)
+}
+
+// Least-squares slope over run order, as a percentage of the mean per run.
+fn format_trend(values: &[f64], mean: f64) -> String {
+    if values.len() < 2 || mean == 0.0 {
+        return "n/a".to_string();
+    }
+    let n = values.len() as f64;
+    let mid = (n - 1.0) / 2.0;
+    let (mut num, mut den) = (0.0, 0.0);
+    for (i, &v) in values.iter().enumerate() {
+        let dx = i as f64 - mid;
+        num += dx * (v - mean);
+        den += dx * dx;
+    }
+    format!("{:+.2}% per run", num / den / mean * 100.0)
+}
+
+// One row per recorded iteration; a suite column leads when there are several.
+fn trace_csv(results: &[BenchResult]) -> String {
+    let several = results.len() > 1;
+    let mut csv = String::from(if several {
+        "suite,iteration_index,nanoseconds\n"
+    } else {
+        "iteration_index,nanoseconds\n"
+    });
+    for r in results {
+        for (index, nanos) in r.trace.iter().enumerate() {
+            if several {
+                csv.push_str(&r.name);
+                csv.push(',');
+            }
+            csv.push_str(&format!("{index},{nanos}\n"));
+        }
+    }
+    csv
+}
+
+fn emit_results(results: &[BenchResult], opts: &BenchOptions) {
+    if let Some(path) = &opts.trace {
+        for r in results {
+            if (r.trace.len() as u64) < r.iterations {
+                eprintln!(
+                    "warning: the {} trace holds only the first {} of {} iterations",
+                    r.name,
+                    r.trace.len(),
+                    r.iterations
+                );
+            }
+        }
+        if let Err(err) = write_atomically(path, trace_csv(results)) {
+            eprintln!("could not write {path}: {err}");
+            std::process::exit(EXIT_FAILURE);
+        }
+    }
+    if let Some(path) = &opts.history {
+        if let Err(err) = append_history(path, results, &opts.parameters) {
+            eprintln!("could not append to {path}: {err}");
+            std::process::exit(EXIT_FAILURE);
+        }
+    }
+    use std::io::Write;
+    let bytes = render_results(results, opts.format, opts.sysinfo);
+    let written = match &opts.output {
+        Some(path) => write_atomically(path, &bytes).map_err(|err| format!("{path}: {err}")),
+        // Keep stdout pure NDJSON while streaming samples.
+        None if opts.stream_samples => std::io::stderr()
+            .write_all(&bytes)
+            .map_err(|err| format!("stderr: {err}")),
+        None => std::io::stdout()
+            .write_all(&bytes)
+            .and_then(|()| std::io::stdout().flush())
+            .map_err(|err| format!("stdout: {err}")),
+    };
+    if let Err(err) = written {
+        eprintln!("could not write {err}");
+        std::process::exit(EXIT_FAILURE);
+    }
+}
+
+// Below this the first-third/last-third gap is within ordinary run-to-run noise.
+const THROTTLE_THRESHOLD: f64 = 0.05;
+
+fn throttle_drop(samples: &[f64]) -> Option<f64> {
+    // Thirds of fewer than two samples are too easily swayed by one window.
+    let third = samples.len() / 3;
+    if third < 2 {
+        return None;
+    }
+    let mean = |window: &[f64]| window.iter().sum::<f64>() / window.len() as f64;
+    let head = mean(&samples[..third]);
+    let tail = mean(&samples[samples.len() - third..]);
+    let drop = (head - tail) / head;
+    (head > 0.0 && drop > THROTTLE_THRESHOLD).then_some(drop)
+}
+
+// Sample standard deviation over the mean of the per-window rates.
+fn coefficient_of_variation(samples: &[f64]) -> Option<f64> {
+    if samples.len() < 2 {
+        return None;
+    }
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean > 0.0).then(|| variance.sqrt() / mean)
+}
+
+fn stats(samples: &[f64]) -> (f64, f64, f64) {
+    if samples.is_empty() {
+        return (0.0, 0.0, 0.0);
+    }
+    let mut min = samples[0];
+    let mut max = samples[0];
+    let mut sum = 0.0;
+    for &v in samples {
+        if v < min {
+            min = v;
+        }
+        if v > max {
+            max = v;
+        }
+        sum += v;
+    }
+    let avg = sum / samples.len() as f64;
+    (min, avg, max)
+}
+
+fn arg_or<T: std::str::FromStr>(args: &[String], index: usize, default: T) -> T {
+    args.get(index)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(default)
+}
+
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != name);
+    args.len() != before
+}
+
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let prefix = format!("{name}=");
+    let mut value = None;
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == name {
+            args.remove(i);
+            if i < args.len() {
+                value = Some(args.remove(i));
+            }
+        } else if let Some(v) = args[i].strip_prefix(&prefix) {
+            value = Some(v.to_string());
+            args.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    value
+}
+
+fn option_or<T: std::str::FromStr>(args: &mut Vec<String>, name: &str, default: T) -> T {
+    take_option(args, name)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(default)
+}
+
+// Decimal places needed to bring the absolute error under `text`, e.g.
+// "1e-50" or "2.5E-8". d correct places (rounded or truncated) leave an error
+// below 10^-d, so d = ceil(-log10(error)). The mantissa and exponent are read
+// separately, so bounds far below f64's range such as 1e-100000 still work.
+fn digits_for_error(text: &str) -> Option<u32> {
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((m, e)) => (m.parse::<f64>().ok()?, e.parse::<i64>().ok()?),
+        None => (text.parse::<f64>().ok()?, 0),
+    };
+    if !(mantissa.is_finite() && mantissa > 0.0) {
+        return None;
+    }
+    let log10 = mantissa.log10() + exponent as f64;
+    Some((-log10).ceil().clamp(0.0, u32::MAX as f64) as u32)
+}
+
+fn take_target_error(args: &mut Vec<String>) -> Option<u32> {
+    let text = take_option(args, "--target-error")?;
+    match digits_for_error(&text) {
+        Some(digits) => {
+            verbose!(1, "target error {text}: {digits} decimal places");
+            Some(digits)
+        }
+        None => {
+            eprintln!("--target-error needs a positive number such as 1e-50, got '{text}'");
+            std::process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+fn profiles_path() -> Option<std::path::PathBuf> {
+    if let Some(path) = env::var_os("GENSUITE_PROFILES") {
+        return Some(path.into());
+    }
+    // An empty XDG_CONFIG_HOME counts as unset, as the XDG spec asks.
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config")))?;
+    Some(config.join("gensuite").join("profiles"))
+}
+
+fn valid_profile_name(name: &str) -> bool {
+    (1..=64).contains(&name.len())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+// One profile per line: the name, then each saved argument, tab-separated.
+fn read_profiles(path: &std::path::Path) -> Vec<(String, Vec<String>)> {
+    let text = std::fs::read_to_string(path).unwrap_or_default();
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t').map(str::to_string);
+            let name = fields.next().filter(|name| !name.is_empty())?;
+            Some((name, fields.collect()))
+        })
+        .collect()
+}
+
+// Handles --save-profile and --profile before anything else looks at the
+// arguments. Saving exits once the profile is written; loading returns the
+// saved arguments with the command-line ones after them, so a flag given
+// again on the command line wins, as with any repeated option.
+fn apply_profiles(mut args: Vec<String>) -> Vec<String> {
+    let save = take_option(&mut args, "--save-profile");
+    let load = take_option(&mut args, "--profile");
+    if save.is_none() && load.is_none() {
+        return args;
+    }
+    let Some(path) = profiles_path() else {
+        eprintln!("cannot locate the profiles file: set GENSUITE_PROFILES or HOME");
+        std::process::exit(EXIT_FAILURE);
+    };
+    for name in save.iter().chain(load.iter()) {
+        if !valid_profile_name(name) {
+            eprintln!("invalid profile name '{name}': use up to 64 letters, digits, '-' or '_'");
+            std::process::exit(EXIT_USAGE);
+        }
+    }
+    let mut profiles = read_profiles(&path);
+    if let Some(name) = load {
+        let Some((_, saved)) = profiles.iter().find(|(n, _)| *n == name) else {
+            eprintln!("unknown profile '{name}' in {}", path.display());
+            std::process::exit(EXIT_USAGE);
+        };
+        args = saved.iter().cloned().chain(args).collect();
+    }
+    let Some(name) = save else {
+        return args;
+    };
+    if args.iter().any(|a| a.contains(['\t', '\n'])) {
+        eprintln!("profile arguments cannot contain tabs or newlines");
+        std::process::exit(EXIT_USAGE);
+    }
+    profiles.retain(|(n, _)| *n != name);
+    profiles.push((name.clone(), args));
+    let text: String = profiles
+        .iter()
+        .map(|(n, saved)| {
+            std::iter::once(n.as_str())
+                .chain(saved.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join("\t")
+                + "\n"
+        })
+        .collect();
+    let written = match path.parent() {
+        Some(dir) => std::fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+    .and_then(|_| write_atomically(&path.to_string_lossy(), &text));
+    if let Err(err) = written {
+        eprintln!("could not write {}: {err}", path.display());
+        std::process::exit(EXIT_FAILURE);
+    }
+    eprintln!("saved profile '{name}' to {}", path.display());
+    std::process::exit(0);
+}
+
+fn take_verbosity(args: &mut Vec<String>) -> u8 {
+    let mut level = 0u8;
+    args.retain(|a| match a.as_str() {
+        "--verbose" => {
+            level += 1;
+            false
+        }
+        _ if a.len() > 1 && a.starts_with('-') && a[1..].bytes().all(|b| b == b'v') => {
+            level += (a.len() - 1) as u8;
+            false
+        }
+        _ => true,
+    });
+    level
+}
+
+const USAGE: &str = "\
+usage: gensuite-helper [-v|-vv] [--errors-to-stdout] [--precompute-primes <limit>] <command> [args]
+       gensuite-helper --save-profile <name> <command> [args]
+       gensuite-helper --profile <name> [extra flags]
+
+commands:
+  pi <digits> [--method machin|agm|chudnovsky|spigot] [--budget <sec>] [--low-memory] [--format text|json|msgpack]
+  pi --target-error <eps> [--method <m>] [--stream]
+  pi [<digits>] --stream [--flush-bytes <n>] [--until <digits>]
+  pi --compare-methods <digits>
+  pi-find <sequence> [--digits <n>]
+  e <digits> [--target-error <eps>] [--format text|json|msgpack]
+  e --cf <count>
+  champernowne <digits> [--base <b>]
+  pi-rational <count> [--max-den <q>] [--digits <d>]
+  convergents <decimal | p/q> [<count>]
+  primes <count> [--count] [--delimiter <s>] [--per-line <n>]
+    [--verify [--verify-rate <r>]]
+  primes --below <n>
+  estimate-nth-prime <n>
+  pi-approx <x>
+  prime-pi-fast <x>
+  mertens <n>
+  totient-sum <n>
+  pseudoprimes <lo> <hi>
+  palindromic-primes <count>
+  repunit-primes <max-n>
+  special-primes --kind wieferich|wilson [<bound>]
+  euclid-mullin <count>
+  factor <n>...
+  is-prime <n> [--test miller-rabin|solovay]
+  random-prime <bits> [--seed <n>]
+  jacobi <a> <n>
+  kronecker <a> <n>
+  legendre <a> <p>
+  sqrt-mod <a> <p>
+  sum-of-squares <n>
+  order <a> <n>
+  modinv <a> <m>
+  radical <n>
+  carmichael <n>
+  amicable <bound>
+  highly-composite [<bound>]
+  abc [<bound>] [--min-quality <q>]
+  verify-primes <file>
+  bernoulli <n>
+  recip <n>
+  rational <p> <q> [max-digits]
+  harmonic <n> [--precision <p> | --target-error <eps>]
+  binomial <n> <k>
+  pascal <n> [--triangle]
+  stern-brocot <p>/<q>
+  partitions <n>
+  factorial <n> [--digits-only] [--low-memory]
+  lcm-range <n> [--digits-only]
+  collatz <n> [--max]
+  farey <n>
+  thue-morse <count> [--sequence thue-morse|paperfolding|rudin-shapiro|period-doubling]
+  ulam <side> [--output <file>]
+  pythagorean <max-c> [--all]
+  isqrt <n>
+  digit-sum <n | a^b | n!>
+  cpuinfo
+  matmul <a-file> <b-file> [--output <file>] [--threads <n>]
+  bench-matmul <sec> [--size <n>] [--repeatable] [--threads <n> [--thread-local]]
+    [--transpose-b] [--random-data [--seed <n>]] [--alpha <a>] [--beta <b>]
+    [--peak <GFLOPS>|auto] [--fit-l2|--fit-l3 [--cache-kb <KiB>]] [--lanes 4|8|16]
+  bench-bigint <sec> [--operand-radix <b>] [--operand-addend <a>[,<b>]]
+    [--operand-digits <n>] [--max-operand-memory <MiB>]
+  bench-pi <sec> [<digits>] [--method <m> | --all-methods]
+  bench-factorial <sec> [<n>]
+  bench-div <sec>
+  bench-sieve <sec> [--threads <n>] [--cold]
+  bench-memory <sec> [--prefault] [--hugepages] [--stride <n> | --random-access [--seed <n>]]
+  bench-all <sec>
+  history-summary <file>
+  msgpack-to-json <file>
+
+bench options:
+  --warmup <sec> --best-of <n> --latency --thermal-throttle-detect --energy
+  --tsv --prometheus --format text|tsv|prometheus|json|msgpack --output <file> --history <file>
+  --max-duration <sec> --yes --sysinfo --stream-samples --trace <file>
+  --max-cv <percent> [--retries <n>]
+";
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // Taken before profiles are expanded so that their errors are routed too.
+    if take_flag(&mut args, "--errors-to-stdout") && !route_stderr_to_stdout() {
+        eprintln!("warning: --errors-to-stdout is not supported here; errors stay on stderr");
+    }
+    let mut args = apply_profiles(args);
+    VERBOSITY.store(take_verbosity(&mut args), Ordering::Relaxed);
+    if let Some(text) = take_option(&mut args, "--precompute-primes") {
+        let limit = text.parse().unwrap_or_else(|_| {
+            eprintln!("--precompute-primes needs a non-negative integer, got '{text}'");
+            std::process::exit(EXIT_USAGE);
+        });
+        small_primes_up_to(limit);
+    }
+    let cmd = if args.is_empty() {
+        String::new()
+    } else {
+        args.remove(0)
+    };
+    match cmd.as_str() {
+        "msgpack-to-json" => {
+            let Some(path) = args.first() else {
+                eprintln!("msgpack-to-json needs a MessagePack file");
+                std::process::exit(EXIT_USAGE);
+            };
+            let bytes = std::fs::read(path).unwrap_or_else(|err| {
+                eprintln!("could not read {path}: {err}");
+                std::process::exit(EXIT_FAILURE);
+            });
+            match msgpack::from_slice::<serde_json::Value>(&bytes) {
+                Ok(value) => println!("{value}"),
+                Err(err) => {
+                    eprintln!("could not decode {path}: {err}");
+                    std::process::exit(EXIT_FAILURE);
+                }
+            }
+        }
+        "history-summary" => {
+            let Some(path) = args.first() else {
+                eprintln!("history-summary needs a history file");
+                std::process::exit(EXIT_USAGE);
+            };
+            match history_summary(path) {
+                Ok(summary) => print!("{summary}"),
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(EXIT_FAILURE);
+                }
+            }
+        }
+        "pi" => {
+            let method = take_option(&mut args, "--method").unwrap_or_else(|| "machin".to_string());
+            let format = take_constant_format(&mut args);
+            let stream = take_flag(&mut args, "--stream");
+            let flush_bytes: usize = option_or(&mut args, "--flush-bytes", 64 * 1024);
+            let budget: Option<f64> =
+                take_option(&mut args, "--budget").map(|text| match text.parse() {
+                    Ok(seconds) if f64::is_finite(seconds) && seconds >= 0.0 => seconds,
+                    _ => {
+                        eprintln!("--budget needs a number of seconds, got '{text}'");
+                        std::process::exit(EXIT_USAGE);
+                    }
+                });
+            let compare: Option<u32> = take_option(&mut args, "--compare-methods").map(|text| {
+                text.parse().unwrap_or_else(|_| {
+                    eprintln!("--compare-methods needs a digit count, got '{text}'");
+                    std::process::exit(EXIT_USAGE);
+                })
+            });
+            if format != OutputFormat::Text && (stream || compare.is_some()) {
+                eprintln!("--format applies to a single computed value, not --stream or --compare-methods");
+                std::process::exit(EXIT_USAGE);
+            }
+            if let Some(digits) = compare {
+                if !compare_pi_methods(digits) {
+                    std::process::exit(EXIT_FAILURE);
+                }
+                return;
+            }
+            let until = take_option(&mut args, "--until");
+            if let Some(pattern) = &until {
+                if !stream {
+                    eprintln!("--until needs --stream");
+                    std::process::exit(EXIT_USAGE);
+                }
+                if pattern.is_empty() || !pattern.bytes().all(|b| b.is_ascii_digit()) {
+                    eprintln!("--until needs a string of decimal digits, got '{pattern}'");
+                    std::process::exit(EXIT_USAGE);
+                }
+            }
+            let low_memory = take_flag(&mut args, "--low-memory");
+            let target_digits = take_target_error(&mut args);
+            // With --until and no count, the stream runs until the match.
+            let unbounded = until.is_some() && target_digits.is_none() && args.is_empty();
+            let digits: u32 = target_digits.unwrap_or_else(|| arg_or(&args, 0, 50));
+            let (mut engine, max_digits) = pi_method_or_exit(&method);
+            if low_memory {
+                if method == "chudnovsky" {
+                    engine = compute_pi_chudnovsky_lean;
+                } else {
+                    eprintln!(
+                        "warning: --low-memory only changes --method chudnovsky; {method} runs as usual"
+                    );
+                }
+            }
+            if stream {
+                let pattern: Option<Vec<u8>> = until
+                    .as_ref()
+                    .map(|p| p.bytes().map(|b| b - b'0').collect());
+                let limit = (!unbounded).then_some(digits as usize);
+                match stream_pi(limit, pattern.as_deref(), flush_bytes.max(1)) {
+                    Ok(found) => {
+                        if let (Some(pattern), false) = (&until, found) {
+                            eprintln!(
+                                "{pattern} does not occur in the first {digits} decimal places"
+                            );
+                            std::process::exit(EXIT_FAILURE);
+                        }
+                    }
+                    // A reader such as `head` closing the pipe early is a
+                    // normal way to stop the stream.
+                    Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => {}
+                    Err(err) => {
+                        eprintln!("could not write digits: {err}");
+                        std::process::exit(EXIT_FAILURE);
+                    }
+                }
+                return;
+            }
+            if let Some(budget) = budget {
+                let budget = Duration::from_secs_f64(budget.max(0.0));
+                let (digits, pi) = pi_within_budget(engine, budget, max_digits);
+                eprintln!(
+                    "pi ({method}): {digits} digits within {:.1}s",
+                    budget.as_secs_f64()
+                );
+                let report = ConstantReport {
+                    schema_version: JSON_SCHEMA_VERSION,
+                    constant: "pi".to_string(),
+                    method: Some(method),
+                    digits,
+                    value: pi,
+                };
+                print_constant(&report, format);
+                return;
+            }
+            if digits > max_digits {
+                eprintln!("the {method} method is limited to {max_digits} digits");
+                std::process::exit(EXIT_USAGE);
+            }
+            let start = Instant::now();
+            let pi = engine(digits);
+            verbose!(
+                1,
+                "pi ({method}, {digits} digits): {:.3}s",
+                start.elapsed().as_secs_f64()
+            );
+            report_peak_memory("pi");
+            let report = ConstantReport {
+                schema_version: JSON_SCHEMA_VERSION,
+                constant: "pi".to_string(),
+                method: Some(method),
+                digits,
+                value: pi,
+            };
+            print_constant(&report, format);
+        }
+        "e" => {
+            let format = take_constant_format(&mut args);
+            let cf: Option<usize> = take_option(&mut args, "--cf").and_then(|s| s.parse().ok());
+            if format != OutputFormat::Text && cf.is_some() {
+                eprintln!("--format applies to the digits of e, not --cf");
+                std::process::exit(EXIT_USAGE);
+            }
+            if let Some(count) = cf {
+                let terms = e_continued_fraction(count);
+                match terms.split_first() {
+                    Some((whole, [])) => println!("[{whole}]"),
+                    Some((whole, rest)) => {
+                        println!("[{whole}; {}]", format_list(rest, ", ", 0))
+                    }
+                    None => println!("[]"),
+                }
+                return;
+            }
+            let digits: u32 = take_target_error(&mut args).unwrap_or_else(|| arg_or(&args, 0, 50));
+            let report = ConstantReport {
+                schema_version: JSON_SCHEMA_VERSION,
+                constant: "e".to_string(),
+                method: None,
+                digits,
+                value: compute_e(digits),
+            };
+            print_constant(&report, format);
+        }
+        "champernowne" => {
+            let base: u32 = option_or(&mut args, "--base", 10);
+            if !(2..=36).contains(&base) {
+                eprintln!("--base must be between 2 and 36");
+                std::process::exit(EXIT_USAGE);
+            }
+            let count: usize = arg_or(&args, 0, 50);
+            println!("0.{}", champernowne_digits(count, base));
+        }
+        "primes" => {
+            let count_only = take_flag(&mut args, "--count");
+            let delimiter = take_option(&mut args, "--delimiter")
+                .map(|d| unescape_delimiter(&d))
+                .unwrap_or_else(|| ", ".to_string());
+            let per_line: usize = option_or(&mut args, "--per-line", 0);
+            let below: Option<usize> =
+                take_option(&mut args, "--below").and_then(|s| s.parse().ok());
+            if let Some(below) = below {
+                println!("{}", sieve_count(below.saturating_sub(1)));
+                return;
+            }
+            let verify = take_flag(&mut args, "--verify");
+            let rate: f64 = option_or(&mut args, "--verify-rate", 1.0);
+            if !(rate > 0.0 && rate <= 1.0) {
+                eprintln!("--verify-rate must be in (0, 1]");
+                std::process::exit(EXIT_USAGE);
+            }
+            let count: usize = arg_or(&args, 0, 15);
+            let primes = generate_primes(count);
+            if verify {
+                let (checked, failures) = verify_generated_primes(&primes, rate);
+                for &index in failures.iter().take(10) {
+                    eprintln!(
+                        "verify: value #{} ({}) is not a prime in sequence",
+                        index + 1,
+                        primes[index]
+                    );
+                }
+                if !failures.is_empty() {
+                    eprintln!(
+                        "verify: {} of {checked} checked values failed; this is a sieve bug",
+                        failures.len()
+                    );
+                    std::process::exit(EXIT_FAILURE);
+                }
+                verbose!(
+                    1,
+                    "verify: {checked} of {} values confirmed prime",
+                    primes.len()
+                );
+            }
+            if count_only {
+                println!("{}", primes.len());
+                return;
+            }
+            println!("{}", format_list(&primes, &delimiter, per_line));
+        }
+        "totient-sum" => {
+            let n: usize = arg_or(&args, 0, 100);
+            println!("{}", totient_sum(n));
+        }
+        "mertens" => {
+            let n: usize = arg_or(&args, 0, 100);
+            println!("{}", mertens(n));
+        }
+        "pi-approx" => {
+            let x: usize = arg_or(&args, 0, 1_000_000);
+            if x < 2 {
+                eprintln!("pi-approx needs x >= 2");
+                std::process::exit(EXIT_USAGE);
+            }
+            let count = sieve_count(x);
+            let li = log_integral(x as f64);
+            let x_ln = x as f64 / (x as f64).ln();
+            let error = |estimate: f64| (estimate - count as f64) / count as f64 * 100.0;
+            println!("pi(x): {count}");
+            println!("li(x): {li:.2} ({:+.3}%)", error(li));
+            println!("x/ln(x): {x_ln:.2} ({:+.3}%)", error(x_ln));
+        }
+        "prime-pi-fast" => {
+            let x = match args.first().map(|s| s.parse::<u64>()) {
+                Some(Ok(x)) if x <= prime_count::PRIME_PI_FAST_MAX => x,
+                Some(Ok(_)) => {
+                    eprintln!(
+                        "prime-pi-fast handles x up to {}",
+                        prime_count::PRIME_PI_FAST_MAX
+                    );
+                    std::process::exit(EXIT_USAGE);
+                }
+                _ => {
+                    eprintln!("prime-pi-fast needs a non-negative integer x");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            println!("{}", prime_count::prime_pi_fast(x));
+        }
+        "palindromic-primes" => {
+            let count: usize = arg_or(&args, 0, 20);
+            println!("{}", format_list(&palindromic_primes(count), ", ", 0));
+        }
+        "euclid-mullin" => {
+            let count: usize = arg_or(&args, 0, 10);
+            if count > EUCLID_MULLIN_MAX_TERMS {
+                eprintln!(
+                    "euclid-mullin computes at most {EUCLID_MULLIN_MAX_TERMS} terms: a(28) has 27 digits and cannot be found by rho"
+                );
+                std::process::exit(EXIT_USAGE);
+            }
+            if count > EUCLID_MULLIN_CHEAP_TERMS {
+                eprintln!(
+                    "warning: terms after a({EUCLID_MULLIN_CHEAP_TERMS}) need Pollard rho on large cofactors and can take several seconds each"
+                );
+            }
+            let mut uncertain = None;
+            for (index, (term, certain)) in euclid_mullin(count).into_iter().enumerate() {
+                println!("{term}");
+                if !certain && uncertain.is_none() {
+                    uncertain = Some(index + 1);
+                }
+            }
+            if let Some(index) = uncertain {
+                eprintln!(
+                    "warning: a({index}) is unverified: a cofactor could not be split within the rho budget, so a smaller prime factor may exist, and later terms depend on it"
+                );
+            }
+        }
+        "repunit-primes" => {
+            let bound: u64 = arg_or(&args, 0, 100);
+            println!("{}", format_list(&repunit_primes(bound), ", ", 0));
+        }
+        "special-primes" => {
+            let kind = take_option(&mut args, "--kind").unwrap_or_default();
+            let (scan, max_bound): (fn(u64) -> Vec<u64>, u64) = match kind.as_str() {
+                "wieferich" => (wieferich_primes, WIEFERICH_MAX_BOUND),
+                "wilson" => (wilson_primes, WILSON_MAX_BOUND),
+                _ => {
+                    eprintln!("special-primes needs --kind wieferich|wilson");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            let bound: u64 = arg_or(&args, 0, 5000);
+            if bound > max_bound {
+                eprintln!("special-primes --kind {kind} scans at most {max_bound}");
+                std::process::exit(EXIT_USAGE);
+            }
+            println!("{}", format_list(&scan(bound), ", ", 0));
+        }
+        "pseudoprimes" => {
+            let lo: u64 = arg_or(&args, 0, 1);
+            let hi: u64 = arg_or(&args, 1, 10_000);
+            if hi > PSEUDOPRIMES_MAX_BOUND || hi.saturating_sub(lo) >= PSEUDOPRIMES_MAX_SPAN {
+                eprintln!(
+                    "pseudoprimes scans at most {PSEUDOPRIMES_MAX_SPAN} numbers at a time, up to {PSEUDOPRIMES_MAX_BOUND}"
+                );
+                std::process::exit(EXIT_USAGE);
+            }
+            let found = fermat_pseudoprimes(lo, hi);
+            println!("{}", format_list(&found, ", ", 0));
+        }
+        "estimate-nth-prime" => {
+            let n: usize = arg_or(&args, 0, 1000);
+            println!("{}", estimate_upper_bound(n));
+        }
+        "factor" => {
+            if args.is_empty() {
+                eprintln!("factor needs at least one positive integer");
+                std::process::exit(EXIT_USAGE);
+            }
+            for arg in &args {
+                match arg.parse::<u64>() {
+                    Ok(n) if n > 0 => println!("{}", format_factorization(n, &factorize(n))),
+                    _ => {
+                        eprintln!("cannot factor '{arg}': expected a positive 64-bit integer");
+                        std::process::exit(EXIT_USAGE);
+                    }
+                }
+            }
+        }
+        "jacobi" => {
+            let a = args.first().and_then(|s| s.parse::<i64>().ok());
+            let n = args.get(1).and_then(|s| s.parse::<u64>().ok());
+            let (Some(a), Some(n)) = (a, n) else {
+                eprintln!("jacobi needs an integer a and an odd positive n");
+                std::process::exit(EXIT_USAGE);
+            };
+            if n.is_multiple_of(2) {
+                eprintln!("jacobi needs an odd positive n, got {n}");
+                std::process::exit(EXIT_USAGE);
+            }
+            println!("{}", jacobi_symbol(a, n));
+        }
+        "legendre" => {
+            let a = args.first().and_then(|s| s.parse::<i64>().ok());
+            let p = args.get(1).and_then(|s| s.parse::<u64>().ok());
+            let (Some(a), Some(p)) = (a, p) else {
+                eprintln!("legendre needs an integer a and an odd prime p");
+                std::process::exit(EXIT_USAGE);
+            };
+            if p == 2 || !is_prime(p) {
+                eprintln!("legendre needs an odd prime p, got {p}; use jacobi or kronecker");
+                std::process::exit(EXIT_USAGE);
+            }
+            println!("{}", legendre_symbol(a, p));
+        }
+        "sqrt-mod" => {
+            let a = args.first().and_then(|s| s.parse::<u64>().ok());
+            let p = args.get(1).and_then(|s| s.parse::<u64>().ok());
+            let (Some(a), Some(p)) = (a, p) else {
+                eprintln!("sqrt-mod needs integers a and a prime p");
+                std::process::exit(EXIT_USAGE);
+            };
+            if !is_prime(p) {
+                eprintln!("sqrt-mod needs a prime p, got {p}");
+                std::process::exit(EXIT_USAGE);
+            }
+            match tonelli_shanks(a, p) {
+                Some(r) if r == 0 || p == 2 => println!("{r}"),
+                Some(r) => println!("{} {}", r.min(p - r), r.max(p - r)),
+                None => println!("none ({a} is not a quadratic residue mod {p})"),
+            }
+        }
+        "sum-of-squares" => {
+            let n = match args.first().map(|s| s.parse::<u64>()) {
+                Some(Ok(n)) => n,
+                _ => {
+                    eprintln!("sum-of-squares needs a non-negative integer");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            match as_sum_of_two_squares(n) {
+                Some((x, y)) => println!("{x}^2 + {y}^2"),
+                None => {
+                    let (p, _) = factorize(n)
+                        .into_iter()
+                        .find(|&(p, e)| p % 4 == 3 && e % 2 == 1)
+                        .expect("only an odd power of a prime 3 mod 4 rules n out");
+                    println!("none ({p} = 3 mod 4 divides {n} to an odd power)");
+                }
+            }
+        }
+        "kronecker" => {
+            let a = args.first().and_then(|s| s.parse::<i64>().ok());
+            let n = args.get(1).and_then(|s| s.parse::<i64>().ok());
+            let (Some(a), Some(n)) = (a, n) else {
+                eprintln!("kronecker needs integers a and n");
+                std::process::exit(EXIT_USAGE);
+            };
+            println!("{}", kronecker_symbol(a, n));
+        }
+        "amicable" => {
+            let bound: u64 = arg_or(&args, 0, 10_000);
+            for (a, b) in amicable_pairs(bound) {
+                println!("{a} {b}");
+            }
+        }
+        "highly-composite" => {
+            let bound: u64 = arg_or(&args, 0, 1000);
+            println!("{}", format_list(&highly_composite(bound), ", ", 0));
+        }
+        "abc" => {
+            let min_quality: f64 = option_or(&mut args, "--min-quality", 1.0);
+            let bound: u64 = arg_or(&args, 0, 1000);
+            if min_quality.is_nan() || min_quality <= 0.0 {
+                eprintln!("--min-quality must be positive");
+                std::process::exit(EXIT_USAGE);
+            }
+            if bound > ABC_MAX_BOUND {
+                eprintln!("abc scans every pair below the bound; the limit is {ABC_MAX_BOUND}");
+                std::process::exit(EXIT_USAGE);
+            }
+            for (a, b, c, quality) in abc_triples(bound, min_quality) {
+                println!("{a} + {b} = {c}\tq = {quality:.4}");
+            }
+        }
+        "order" => {
+            let a = args.first().and_then(|s| s.parse::<u64>().ok());
+            let n = args.get(1).and_then(|s| s.parse::<u64>().ok());
+            let (Some(a), Some(n)) = (a, n) else {
+                eprintln!("order needs integers a and n > 0");
+                std::process::exit(EXIT_USAGE);
+            };
+            if n == 0 {
+                eprintln!("order needs a positive modulus");
+                std::process::exit(EXIT_USAGE);
+            }
+            match multiplicative_order(a, n) {
+                Some(k) => println!("{k}"),
+                None => println!("none (gcd({a}, {n}) = {})", gcd_u64(a, n)),
+            }
+        }
+        "modinv" => {
+            use num_traits::Signed;
+
+            let parsed: Vec<_> = args
+                .iter()
+                .take(2)
+                .map(|s| parse_big_expression(s))
+                .collect();
+            let (a, m) = match &parsed[..] {
+                [Ok(a), Ok(m)] if m.is_positive() => (a.clone(), m.clone()),
+                [Ok(_), Ok(_)] => {
+                    eprintln!("modinv needs a positive modulus");
+                    std::process::exit(EXIT_USAGE);
+                }
+                [Err(err), _] | [_, Err(err)] => {
+                    eprintln!("{err}");
+                    std::process::exit(EXIT_USAGE);
+                }
+                _ => {
+                    eprintln!("modinv needs integers a and m");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            match mod_inverse(a.clone(), m.clone()) {
+                Some(inverse) => println!("{inverse}"),
+                None => println!(
+                    "none (gcd({a}, {m}) = {})",
+                    num_integer::Integer::gcd(&a, &m)
+                ),
+            }
+        }
+        "carmichael" => {
+            let n = match args.first().map(|s| s.parse::<u64>()) {
+                Some(Ok(n)) if n > 0 => n,
+                _ => {
+                    eprintln!("carmichael needs a positive integer");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            println!("{}", carmichael_lambda(n));
+        }
+        "radical" => {
+            let n = match args.first().map(|s| s.parse::<u64>()) {
+                Some(Ok(n)) if n > 0 => n,
+                _ => {
+                    eprintln!("radical needs a positive integer");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            println!("{}", radical(n));
+        }
+        "random-prime" => {
+            let seed: Option<u64> = take_option(&mut args, "--seed").map(|text| {
+                text.parse().unwrap_or_else(|_| {
+                    eprintln!("--seed needs a non-negative integer, got '{text}'");
+                    std::process::exit(EXIT_USAGE);
+                })
+            });
+            let bits = match args.first().map(|s| s.parse::<u64>()) {
+                Some(Ok(bits)) if bits >= 2 => bits,
+                _ => {
+                    eprintln!("random-prime needs a bit length of at least 2");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            // Without --seed every run differs; -v prints the seed used.
+            let seed = seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(DEFAULT_SEED, |since| since.as_nanos() as u64)
+                    ^ std::process::id() as u64
+            });
+            let (prime, tries) = random_prime(bits, &mut Rng::new(seed));
+            verbose!(1, "random-prime: seed {seed}, {tries} candidates");
+            println!("{prime}");
+        }
+        "is-prime" => {
+            let test =
+                take_option(&mut args, "--test").unwrap_or_else(|| "miller-rabin".to_string());
+            let n: num_bigint::BigInt = match args.first().map(|s| s.parse()) {
+                Some(Ok(n)) => n,
+                _ => {
+                    eprintln!("is-prime needs an integer");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            let prime = match test.as_str() {
+                "miller-rabin" => is_probable_prime_big(&n),
+                "solovay" => {
+                    use num_traits::{Signed, ToPrimitive};
+                    match n.to_u64() {
+                        Some(small) => is_prime_solovay(small),
+                        None if n.is_negative() => false,
+                        None => {
+                            eprintln!("the solovay test is limited to 64-bit integers");
+                            std::process::exit(EXIT_USAGE);
+                        }
+                    }
+                }
+                other => {
+                    eprintln!("unknown primality test: {other} (expected miller-rabin or solovay)");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            if prime {
+                println!("{n} is prime");
+            } else {
+                println!("{n} is not prime");
+            }
+        }
+        "verify-primes" => {
+            let Some(path) = args.first() else {
+                eprintln!("verify-primes needs a file path");
+                std::process::exit(EXIT_USAGE);
+            };
+            let text = match std::fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(err) => {
+                    eprintln!("could not read {path}: {err}");
+                    std::process::exit(EXIT_FAILURE);
+                }
+            };
+            match verify_prime_list(&text) {
+                PrimeListCheck::AllPrime(count) => println!("All {count} entries are prime"),
+                PrimeListCheck::NotPrime {
+                    value,
+                    index,
+                    line,
+                    column,
+                } => {
+                    println!("Not prime: {value} (entry {index}, line {line}, column {column})");
+                    std::process::exit(EXIT_FAILURE);
+                }
+                PrimeListCheck::ParseError {
+                    token,
+                    line,
+                    column,
+                } => {
+                    eprintln!("could not parse '{token}' at line {line}, column {column}");
+                    std::process::exit(EXIT_FAILURE);
+                }
+            }
+        }
+        "bernoulli" => {
+            let n: u32 = arg_or(&args, 0, 12);
+            let (num, den) = bernoulli(n);
+            println!("{num}/{den}");
+        }
+        "recip" => {
+            let n: u64 = arg_or(&args, 0, 7);
+            if n == 0 {
+                eprintln!("recip needs a positive integer");
+                std::process::exit(EXIT_USAGE);
+            }
+            let (digits, period) = reciprocal_decimal(n);
+            println!("{digits}\nPeriod: {period}");
+        }
+        "rational" => {
+            let p = args
+                .first()
+                .and_then(|s| s.parse::<num_bigint::BigInt>().ok());
+            let q = args
+                .get(1)
+                .and_then(|s| s.parse::<num_bigint::BigInt>().ok());
+            let (Some(p), Some(q)) = (p, q) else {
+                eprintln!("rational needs integers p and q");
+                std::process::exit(EXIT_USAGE);
+            };
+            if q == num_bigint::BigInt::from(0u32) {
+                eprintln!("rational needs a nonzero denominator");
+                std::process::exit(EXIT_USAGE);
+            }
+            let max_digits: u32 = arg_or(&args, 2, 100);
+            println!("{}", rational_decimal(p, q, max_digits));
+        }
+        "pi-find" => {
+            let digits: u32 = option_or(&mut args, "--digits", 100_000);
+            let pattern = match args.first() {
+                Some(p) if !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()) => p.clone(),
+                _ => {
+                    eprintln!("pi-find needs a sequence of decimal digits");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            match pi_find(&pattern, digits) {
+                Some(position) => println!("{pattern} found at digit {position}"),
+                None => println!("{pattern} not found in the first {digits} digits"),
+            }
+        }
+        "pi-rational" => {
+            let max_den: Option<u64> =
+                take_option(&mut args, "--max-den").map(|text| match text.parse() {
+                    Ok(q) if q > 0 => q,
+                    _ => {
+                        eprintln!("--max-den needs a positive integer, got '{text}'");
+                        std::process::exit(EXIT_USAGE);
+                    }
+                });
+            let digits: u32 = option_or(&mut args, "--digits", 100);
+            let count: usize = arg_or(&args, 0, 10);
+            for approx in pi_rational(count, max_den, digits) {
+                println!("{}/{}\t{:.3e}", approx.num, approx.den, approx.error);
+            }
+        }
+        "convergents" => {
+            let Some(text) = args.first() else {
+                eprintln!("convergents needs a decimal like 3.14159 or a fraction like 355/113");
+                std::process::exit(EXIT_USAGE);
+            };
+            let Some((num, den)) = parse_rational(text) else {
+                eprintln!(
+                    "convergents: '{text}' is not a decimal like 3.14159 or a fraction p/q with q != 0"
+                );
+                std::process::exit(EXIT_USAGE);
+            };
+            let count: usize = arg_or(&args, 1, usize::MAX);
+            for approx in convergent_approximations(&num, &den, count) {
+                println!("{}/{}\t{:.3e}", approx.num, approx.den, approx.error);
+            }
+        }
+        "harmonic" => {
+            let precision: u32 = option_or(&mut args, "--precision", 20);
+            let precision = take_target_error(&mut args).unwrap_or(precision);
+            let n: u64 = arg_or(&args, 0, 10);
+            if n > 100_000 {
+                eprintln!(
+                    "warning: exact H_{n} needs very large fractions and may take a long time"
+                );
+            }
+            let (num, den) = harmonic_exact(n);
+            println!("Exact: {num}/{den}");
+            println!("Decimal: {}", fraction_to_decimal(&num, &den, precision));
+            println!("Float: {}", harmonic_float(n));
+        }
+        "binomial" => {
+            let n: u64 = arg_or(&args, 0, 10);
+            let k: u64 = arg_or(&args, 1, 3);
+            println!("{}", binomial(n, k));
+        }
+        "pascal" => {
+            let triangle = take_flag(&mut args, "--triangle");
+            let n: u64 = arg_or(&args, 0, 10);
+            let first = if triangle { 0 } else { n };
+            for m in first..=n {
+                println!("{}", format_list(&pascal_row(m), " ", 0));
+            }
+        }
+        "partitions" => {
+            let n: u64 = arg_or(&args, 0, 100);
+            println!("{}", partition_count(n));
+        }
+        "stern-brocot" => {
+            // Accepts either "p/q" or "p q".
+            let parts: Vec<&str> = match args.first() {
+                Some(first) if first.contains('/') => first.splitn(2, '/').collect(),
+                _ => args.iter().take(2).map(String::as_str).collect(),
+            };
+            let (p, q) = match parts[..] {
+                [p, q] => match (p.trim().parse::<u64>(), q.trim().parse::<u64>()) {
+                    (Ok(p), Ok(q)) if p > 0 && q > 0 => (p, q),
+                    _ => {
+                        eprintln!("stern-brocot needs a positive numerator and denominator");
+                        std::process::exit(EXIT_USAGE);
+                    }
+                },
+                _ => {
+                    eprintln!("stern-brocot needs a fraction such as 3/5");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            let path = stern_brocot_path(p, q);
+            if path.is_empty() {
+                println!("{p}/{q}: root");
+            } else {
+                println!("{p}/{q}: {path}");
+            }
+        }
+        "factorial" => {
+            let digits_only = take_flag(&mut args, "--digits-only");
+            let low_memory = take_flag(&mut args, "--low-memory");
+            let n: u64 = arg_or(&args, 0, 10);
+            let value = if low_memory {
+                factorial_low_memory(n)
+            } else {
+                factorial(n)
+            };
+            report_peak_memory("factorial");
+            let value = value.to_string();
+            if digits_only {
+                println!("{}", value.len());
+            } else {
+                println!("{value}");
+            }
+        }
+        "lcm-range" => {
+            let digits_only = take_flag(&mut args, "--digits-only");
+            let n: u64 = arg_or(&args, 0, 10);
+            let value = lcm_1_to_n(n).to_string();
+            if digits_only {
+                println!("{}", value.len());
+            } else {
+                println!("{value}");
+            }
+        }
+        "collatz" => {
+            let max = take_flag(&mut args, "--max");
+            let n: u64 = arg_or(&args, 0, 27);
+            if n == 0 {
+                eprintln!("collatz needs a positive integer");
+                std::process::exit(EXIT_USAGE);
+            }
+            if max {
+                // Reverse(start) makes ties go to the smallest start.
+                let (steps, longest) = (1..=n)
+                    .map(|start| (collatz_steps(start), std::cmp::Reverse(start)))
+                    .max()
+                    .map(|(steps, start)| (steps, start.0))
+                    .unwrap_or((0, 1));
+                println!("Longest up to {n}: {longest} ({steps} steps)");
+            } else {
+                println!("{n} reaches 1 in {} steps", collatz_steps(n));
+            }
+        }
+        "ulam" => {
+            let output = take_option(&mut args, "--output");
+            let side: usize = arg_or(&args, 0, 201);
+            let pbm = ulam_spiral_pbm(side);
+            let written = match &output {
+                Some(path) => write_atomically(path, &pbm),
+                None => {
+                    use std::io::Write;
+                    std::io::stdout().lock().write_all(&pbm)
+                }
+            };
+            if let Err(err) = written {
+                let target = output.as_deref().unwrap_or("stdout");
+                eprintln!("could not write {target}: {err}");
+                std::process::exit(EXIT_FAILURE);
+            }
+        }
+        "thue-morse" => {
+            let sequence =
+                take_option(&mut args, "--sequence").unwrap_or_else(|| "thue-morse".to_string());
+            let Some(&(_, term)) = AUTOMATIC_SEQUENCES
+                .iter()
+                .find(|(name, _)| *name == sequence)
+            else {
+                eprintln!(
+                    "unknown sequence: {sequence} (expected thue-morse, paperfolding, rudin-shapiro or period-doubling)"
+                );
+                std::process::exit(EXIT_USAGE);
+            };
+            let count: u64 = arg_or(&args, 0, 32);
+            let terms: String = (0..count).map(|n| char::from(b'0' + term(n))).collect();
+            println!("{terms}");
+        }
+        "farey" => {
+            let n: u64 = arg_or(&args, 0, 5);
+            let terms: Vec<String> = farey_sequence(n)
+                .into_iter()
+                .map(|(a, b)| format!("{a}/{b}"))
+                .collect();
+            println!("{}", format_list(&terms, ", ", 0));
+        }
+        "pythagorean" => {
+            let all = take_flag(&mut args, "--all");
+            let max_c: u64 = match args.first().map(|s| s.parse()) {
+                Some(Ok(max_c)) if max_c > 0 => max_c,
+                _ => {
+                    eprintln!("pythagorean needs a positive hypotenuse bound");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            let mut triples = primitive_triples(max_c);
+            if all {
+                triples = triples
+                    .into_iter()
+                    .flat_map(|(a, b, c)| (1..=max_c / c).map(move |k| (k * a, k * b, k * c)))
+                    .collect();
+                triples.sort_unstable_by_key(|&(a, b, c)| (c, a, b));
+            }
+            for (a, b, c) in triples {
+                println!("{a} {b} {c}");
+            }
+        }
+        "isqrt" => {
+            let n: num_bigint::BigInt = match args.first().map(|s| s.parse()) {
+                Some(Ok(n)) if n >= num_bigint::BigInt::from(0u32) => n,
+                _ => {
+                    eprintln!("isqrt needs a non-negative integer");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            let (root, rem) = isqrt_rem(&n);
+            println!("Root: {root}\nRemainder: {rem}");
+        }
+        "digit-sum" => {
+            let Some(text) = args.first() else {
+                eprintln!("digit-sum needs a number, a^b or n!");
+                std::process::exit(EXIT_USAGE);
+            };
+            let n = parse_big_expression(text).unwrap_or_else(|err| {
+                eprintln!("{err}");
+                std::process::exit(EXIT_USAGE);
+            });
+            println!(
+                "Digit sum: {}\nDigital root: {}",
+                digit_sum(&n),
+                digital_root(&n)
+            );
+        }
+        "matmul" => {
+            let threads = option_or(&mut args, "--threads", 1usize).max(1);
+            let output = take_option(&mut args, "--output");
+            let [left, right] = &args[..] else {
+                eprintln!("matmul needs two matrix files");
+                std::process::exit(EXIT_USAGE);
+            };
+            let load = |path: &String| {
+                let text = std::fs::read_to_string(path).unwrap_or_else(|err| {
+                    eprintln!("could not read {path}: {err}");
+                    std::process::exit(EXIT_FAILURE);
+                });
+                parse_matrix(&text).unwrap_or_else(|err| {
+                    eprintln!("{path}: {err}");
+                    std::process::exit(EXIT_FAILURE);
+                })
+            };
+            let (a, b) = (load(left), load(right));
+            if a.cols != b.rows {
+                eprintln!(
+                    "cannot multiply a {}x{} matrix by a {}x{} one: {} columns do not match {} rows",
+                    a.rows, a.cols, b.rows, b.cols, a.cols, b.rows
+                );
+                std::process::exit(EXIT_FAILURE);
+            }
+            let mut c = Matrix {
+                rows: a.rows,
+                cols: b.cols,
+                values: vec![0.0; a.rows * b.cols],
+            };
+            matmul_threaded(&a.values, &b.values, &mut c.values, a.cols, b.cols, threads);
+            let text = format_matrix(&c);
+            match output {
+                Some(path) => {
+                    if let Err(err) = write_atomically(&path, &text) {
+                        eprintln!("could not write {path}: {err}");
+                        std::process::exit(EXIT_FAILURE);
+                    }
+                }
+                None => print!("{text}"),
+            }
+        }
+        "cpuinfo" => {
+            let features = simd_features();
+            if features.is_empty() {
+                println!(
+                    "SIMD feature detection is not supported on {}",
+                    std::env::consts::ARCH
+                );
+                return;
+            }
+            for (name, available) in features {
+                println!("{name}: {}", if available { "yes" } else { "no" });
+            }
+        }
+        "bench-matmul" => {
+            let opts = BenchOptions::from_args(&mut args);
+            let size_given = args.iter().any(|arg| arg == "--size");
+            let mut matmul = MatmulOptions {
+                repeatable: take_flag(&mut args, "--repeatable"),
+                threads: option_or(&mut args, "--threads", 1usize).max(1),
+                random_seed: take_flag(&mut args, "--random-data")
+                    .then(|| option_or(&mut args, "--seed", DEFAULT_SEED)),
+                thread_local: take_flag(&mut args, "--thread-local"),
+                transpose_b: false,
+                size: option_or(&mut args, "--size", 128usize).max(1),
+                gemm: None,
+                lanes: None,
+            };
+            if let Some(text) = take_option(&mut args, "--lanes") {
+                let Some(&(lanes, feature)) = MATMUL_LANES
+                    .iter()
+                    .find(|(lanes, _)| text.parse() == Ok(*lanes))
+                else {
+                    eprintln!("--lanes must be 4, 8 or 16, got '{text}'");
+                    std::process::exit(EXIT_USAGE);
+                };
+                if !simd_features().contains(&(feature, true)) {
+                    eprintln!(
+                        "--lanes {lanes} needs {feature}, which this CPU does not report; \
+                         run `gensuite-helper cpuinfo` to see the supported vector extensions"
+                    );
+                    std::process::exit(EXIT_USAGE);
+                }
+                matmul.lanes = Some(lanes);
+            }
+            let fit_level = match (
+                take_flag(&mut args, "--fit-l2"),
+                take_flag(&mut args, "--fit-l3"),
+            ) {
+                (true, true) => {
+                    eprintln!("--fit-l2 and --fit-l3 cannot be combined");
+                    std::process::exit(EXIT_USAGE);
+                }
+                (true, false) => Some(2),
+                (false, true) => Some(3),
+                (false, false) => None,
+            };
+            let cache_kib: Option<u64> =
+                take_option(&mut args, "--cache-kb").map(|text| match text.parse() {
+                    Ok(kib) if kib > 0 => kib,
+                    _ => {
+                        eprintln!("--cache-kb needs a positive size in KiB, got '{text}'");
+                        std::process::exit(EXIT_USAGE);
+                    }
+                });
+            let mut cache_fit = None;
+            if let Some(level) = fit_level {
+                if size_given {
+                    eprintln!("--fit-l{level} picks the size itself; drop --size");
+                    std::process::exit(EXIT_USAGE);
+                }
+                let (kib, source) = match cache_kib {
+                    Some(kib) => (kib, "given"),
+                    None => match cache_size_kib(level) {
+                        Some(kib) => (kib, "detected"),
+                        None => {
+                            eprintln!(
+                                "cannot detect the L{level} cache size; pass --cache-kb <KiB>"
+                            );
+                            std::process::exit(EXIT_USAGE);
+                        }
+                    },
+                };
+                let n = matmul_size_for_cache(kib);
+                matmul.size = n;
+                cache_fit = Some(format!(
+                    "L{level} of {kib} KiB ({source}); A, B and C take {} KiB",
+                    3 * n * n * 8 / 1024
+                ));
+            } else if cache_kib.is_some() {
+                eprintln!("--cache-kb needs --fit-l2 or --fit-l3");
+                std::process::exit(EXIT_USAGE);
+            }
+            let transpose_b = take_flag(&mut args, "--transpose-b");
+            if transpose_b && matmul.lanes.is_some() {
+                eprintln!("--lanes applies to the row-major kernel only; drop --transpose-b");
+                std::process::exit(EXIT_USAGE);
+            }
+            let peak = match take_option(&mut args, "--peak").as_deref() {
+                None => None,
+                Some("auto") => {
+                    let estimate = estimate_peak_gflops(matmul.threads);
+                    if estimate.is_none() {
+                        eprintln!(
+                            "warning: cannot estimate peak FLOP/s here; pass --peak <GFLOPS> to get the fraction"
+                        );
+                    }
+                    estimate
+                }
+                Some(text) => match text.parse::<f64>() {
+                    Ok(gflops) if gflops > 0.0 && gflops.is_finite() => {
+                        Some((gflops, "given".to_string()))
+                    }
+                    _ => {
+                        eprintln!("--peak needs a positive GFLOP/s figure or 'auto', got '{text}'");
+                        std::process::exit(EXIT_USAGE);
+                    }
+                },
+            };
+            let alpha = take_option(&mut args, "--alpha");
+            let beta = take_option(&mut args, "--beta");
+            if alpha.is_some() || beta.is_some() {
+                let coefficient = |value: Option<String>, name: &str, default: f64| match value {
+                    None => default,
+                    Some(text) => match text.parse::<f64>() {
+                        Ok(v) if v.is_finite() => v,
+                        _ => {
+                            eprintln!("{name} needs a finite number, got '{text}'");
+                            std::process::exit(EXIT_USAGE);
+                        }
+                    },
+                };
+                let alpha = coefficient(alpha, "--alpha", 1.0);
+                let beta = coefficient(beta, "--beta", 0.0);
+                if matmul.repeatable && beta != 0.0 {
+                    eprintln!(
+                        "--repeatable needs --beta 0: beta*C carries state from one iteration to the next"
+                    );
+                    std::process::exit(EXIT_USAGE);
+                }
+                matmul.gemm = Some((alpha, beta));
+            }
+            let seconds: u64 = arg_or(&args, 0, 60);
+            confirm_duration(
+                bench_duration(seconds, &opts).saturating_mul(1 + transpose_b as u64),
+                &opts,
+            );
+            let mut results = vec![run_bench(seconds, &opts, |s| bench_matmul(s, &matmul))];
+            // The transposed kernel runs after the plain one, and its result
+            // reports the difference between the two.
+            if transpose_b && !interrupted() {
+                let transposed = MatmulOptions {
+                    transpose_b: true,
+                    ..matmul
+                };
+                let mut result = run_bench(seconds, &opts, |s| bench_matmul(s, &transposed));
+                if results[0].overall > 0.0 {
+                    result.detail(
+                        "Vs row-major B",
+                        format!(
+                            "{:+.1}%",
+                            (result.overall / results[0].overall - 1.0) * 100.0
+                        ),
+                    );
+                }
+                results.push(result);
+            }
+            if let Some(fit) = &cache_fit {
+                for result in &mut results {
+                    result.detail("Cache fit", fit);
+                }
+            }
+            if let Some((gflops, source)) = &peak {
+                for result in &mut results {
+                    result.detail(
+                        "Of peak",
+                        format!(
+                            "{:.1}% of {gflops:.1} GFLOP/s ({source})",
+                            result.overall / gflops * 100.0
+                        ),
+                    );
+                }
+            }
+            emit_results(&results, &opts);
+        }
+        "bench-bigint" => {
+            let opts = BenchOptions::from_args(&mut args);
+            let mut spec = OperandSpec::default();
+            spec.radix = option_or(&mut args, "--operand-radix", spec.radix);
+            if let Some(addends) = take_option(&mut args, "--operand-addend") {
+                let parsed: Result<Vec<u32>, _> =
+                    addends.split(',').map(|s| s.trim().parse()).collect();
+                spec.addends = match parsed.as_deref() {
+                    Ok([both]) => (*both, *both),
+                    Ok([a, b]) => (*a, *b),
+                    _ => {
+                        eprintln!(
+                            "--operand-addend needs one or two non-negative integers separated by a comma, got '{addends}'"
+                        );
+                        std::process::exit(EXIT_USAGE);
+                    }
+                };
+            }
+            spec.digits = option_or(&mut args, "--operand-digits", spec.digits);
+            if spec.radix < 2 {
+                eprintln!("--operand-radix must be at least 2");
+                std::process::exit(EXIT_USAGE);
+            }
+            let cap: u64 = option_or(
+                &mut args,
+                "--max-operand-memory",
+                DEFAULT_MAX_OPERAND_MEMORY_MIB,
+            );
+            let estimate = spec.estimated_bytes() / (1u64 << 20) as f64;
+            if estimate > cap as f64 {
+                if !opts.assume_yes {
+                    eprintln!(
+                        "refusing to build {}-digit operands: needs ~{estimate:.0} MiB, over the {cap} MiB limit; pass --force, or raise --max-operand-memory",
+                        spec.digits
+                    );
+                    std::process::exit(EXIT_USAGE);
+                }
+                eprintln!("warning: operands need ~{estimate:.0} MiB, over the {cap} MiB limit");
+            }
+            let seconds: u64 = arg_or(&args, 0, 60);
+            confirm_duration(bench_duration(seconds, &opts), &opts);
+            emit_results(
+                &[run_bench(seconds, &opts, |s| bench_bigint(s, &spec))],
+                &opts,
+            );
+        }
+        "bench-pi" => {
+            let opts = BenchOptions::from_args(&mut args);
+            let all_methods = take_flag(&mut args, "--all-methods");
+            let method = take_option(&mut args, "--method");
+            let seconds: u64 = arg_or(&args, 0, 60);
+            let digits: u32 = arg_or(&args, 1, 10_000);
+            if all_methods {
+                if method.is_some() {
+                    eprintln!("--all-methods and --method cannot be combined");
+                    std::process::exit(EXIT_USAGE);
+                }
+                let engines = PI_METHODS.iter().filter(|m| digits <= m.2).count();
+                confirm_duration(
+                    bench_duration(seconds, &opts).saturating_mul(engines as u64),
+                    &opts,
+                );
+                let mut results = Vec::new();
+                for (name, engine, suite) in spot_checked_pi_methods(digits) {
+                    if interrupted() {
+                        break;
+                    }
+                    results.push(run_bench(seconds, &opts, |s| {
+                        bench_pi(s, suite, name, engine, digits)
+                    }));
+                }
+                emit_results(&results, &opts);
+                if opts.format == OutputFormat::Text {
+                    let table = pi_methods_table(&results, digits);
+                    if opts.stream_samples {
+                        eprint!("\n{table}");
+                    } else {
+                        print!("\n{table}");
+                    }
+                }
+                return;
+            }
+            let method = method.unwrap_or_else(|| "machin".to_string());
+            let (engine, max_digits) = pi_method_or_exit(&method);
+            if digits > max_digits {
+                eprintln!("the {method} method is limited to {max_digits} digits");
+                std::process::exit(EXIT_USAGE);
+            }
+            confirm_duration(bench_duration(seconds, &opts), &opts);
+            emit_results(
+                &[run_bench(seconds, &opts, |s| {
+                    bench_pi(s, "pi", &method, engine, digits)
+                })],
+                &opts,
+            );
+        }
+        "bench-factorial" => {
+            let opts = BenchOptions::from_args(&mut args);
+            let seconds: u64 = arg_or(&args, 0, 60);
+            let n: u64 = arg_or(&args, 1, 20_000);
+            confirm_duration(bench_duration(seconds, &opts), &opts);
+            emit_results(
+                &[run_bench(seconds, &opts, |s| bench_factorial(s, n))],
+                &opts,
+            );
+        }
+        "bench-div" => {
+            let opts = BenchOptions::from_args(&mut args);
+            let seconds: u64 = arg_or(&args, 0, 60);
+            confirm_duration(bench_duration(seconds, &opts).saturating_mul(2), &opts);
+            let mut results = vec![run_bench(seconds, &opts, |s| bench_div(s, true))];
+            if !interrupted() {
+                results.push(run_bench(seconds, &opts, |s| bench_div(s, false)));
+            }
+            emit_results(&results, &opts);
+        }
+        "bench-sieve" => {
+            let opts = BenchOptions::from_args(&mut args);
+            let threads = option_or(&mut args, "--threads", 1usize).max(1);
+            let cold = take_flag(&mut args, "--cold");
+            let seconds: u64 = arg_or(&args, 0, 60);
+            // The cold pass spends the same time again after every run.
+            confirm_duration(
+                bench_duration(seconds, &opts).saturating_mul(1 + cold as u64),
+                &opts,
+            );
+            let result = run_bench(seconds, &opts, |s| bench_sieve(s, threads, cold));
+            emit_results(&[result], &opts);
+        }
+        "bench-memory" => {
+            let opts = BenchOptions::from_args(&mut args);
+            let random = take_flag(&mut args, "--random-access");
+            let stride = option_or(&mut args, "--stride", 1usize).max(1);
+            let memory = MemoryOptions {
+                prefault: take_flag(&mut args, "--prefault"),
+                hugepages: take_flag(&mut args, "--hugepages"),
+                access: if random {
+                    Access::Random(option_or(&mut args, "--seed", DEFAULT_SEED))
+                } else if stride > 1 {
+                    Access::Stride(stride)
+                } else {
+                    Access::Sequential
+                },
+            };
+            let seconds: u64 = arg_or(&args, 0, 60);
+            confirm_duration(bench_duration(seconds, &opts), &opts);
+            let result = run_bench(seconds, &opts, |s| bench_memory(s, &memory));
+            emit_results(&[result], &opts);
+        }
+        "bench-all" => {
+            let opts = BenchOptions::from_args(&mut args);
+            let seconds: u64 = arg_or(&args, 0, 60);
+            let spec = OperandSpec::default();
+            let matmul = MatmulOptions::default();
+            let memory = MemoryOptions::default();
+            confirm_duration(bench_duration(seconds, &opts).saturating_mul(4), &opts);
+            let mut results = vec![run_bench(seconds, &opts, |s| bench_matmul(s, &matmul))];
+            if !interrupted() {
+                results.push(run_bench(seconds, &opts, |s| bench_bigint(s, &spec)));
+            }
+            if !interrupted() {
+                results.push(run_bench(seconds, &opts, |s| bench_sieve(s, 1, false)));
+            }
+            if !interrupted() {
+                results.push(run_bench(seconds, &opts, |s| bench_memory(s, &memory)));
+            }
+            emit_results(&results, &opts);
+        }
+        _ => {
+            if !cmd.is_empty() {
+                eprintln!("unknown command: {cmd}");
+            }
+            eprint!("{USAGE}");
+            std::process::exit(EXIT_USAGE);
+        }
+    }
+    if interrupted() {
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agm_pi_matches_machin_and_chudnovsky() {
+        let machin = compute_pi(1000);
+        assert_eq!(compute_pi_agm(1000), machin);
+        assert_eq!(compute_pi_chudnovsky(1000), machin);
+    }
+
+    #[test]
+    fn isqrt_handles_squares_neighbours_and_large_values() {
+        use num_bigint::BigInt;
+
+        for root in [0u32, 1, 2, 3, 10, 65535, 1_000_000] {
+            let square = BigInt::from(root) * root;
+            assert_eq!(isqrt(&square), BigInt::from(root));
+            assert_eq!(isqrt_rem(&square).1, BigInt::from(0u32));
+            if root > 0 {
+                let below = &square - 1u32;
+                assert_eq!(isqrt(&below), BigInt::from(root - 1));
+                assert_eq!(isqrt_rem(&below).1, below - BigInt::from(root - 1).pow(2));
+            }
+        }
+        let big = pow10(301) + 12345u32;
+        let (root, rem) = isqrt_rem(&big);
+        assert!(&root * &root <= big);
+        assert!((&root + 1u32) * (&root + 1u32) > big);
+        assert_eq!(rem, &big - &root * &root);
+    }
+
+    #[test]
+    fn bernoulli_small_indices() {
+        use num_bigint::BigInt;
+
+        assert_eq!(bernoulli(0), (BigInt::from(1), BigInt::from(1)));
+        assert_eq!(bernoulli(1), (BigInt::from(-1), BigInt::from(2)));
+        assert_eq!(bernoulli(2), (BigInt::from(1), BigInt::from(6)));
+        assert_eq!(bernoulli(12), (BigInt::from(-691), BigInt::from(2730)));
+    }
+
+    #[test]
+    fn reciprocal_periods() {
+        assert_eq!(reciprocal_decimal(7), ("0.(142857)".to_string(), 6));
+        assert_eq!(reciprocal_decimal(3), ("0.(3)".to_string(), 1));
+        assert_eq!(reciprocal_decimal(8), ("0.125".to_string(), 0));
+    }
+
+    #[test]
+    fn nth_prime_estimate_is_an_upper_bound() {
+        let primes = sieve_primes(2_000_000);
+        for n in [1, 2, 5, 6, 10, 100, 1000, 10_000, 100_000] {
+            assert!(
+                estimate_upper_bound(n) as u64 >= primes[n - 1],
+                "estimate for n = {n} is below p_n = {}",
+                primes[n - 1]
+            );
+        }
+    }
+
+    #[test]
+    fn primes_with_newline_delimiter() {
+        let primes = generate_primes(5);
+        assert_eq!(
+            format_list(&primes, &unescape_delimiter("\\n"), 0),
+            "2\n3\n5\n7\n11"
+        );
+        assert_eq!(format_list(&primes, " ", 2), "2 3\n5 7\n11");
+    }
+
+    #[test]
+    fn pi_convergents_include_355_113() {
+        let found: Vec<(String, String)> = pi_rational(6, None, 50)
+            .into_iter()
+            .map(|a| (a.num.to_string(), a.den.to_string()))
+            .collect();
+        assert!(found.contains(&("22".to_string(), "7".to_string())));
+        assert!(found.contains(&("355".to_string(), "113".to_string())));
+    }
+
+    #[test]
+    fn harmonic_exact_small_values() {
+        use num_bigint::BigInt;
+
+        assert_eq!(harmonic_exact(1), (BigInt::from(1), BigInt::from(1)));
+        assert_eq!(harmonic_exact(4), (BigInt::from(25), BigInt::from(12)));
+    }
+
+    #[test]
+    fn factorizations_share_the_small_prime_cache() {
+        let before = small_primes_up_to(TRIAL_DIVISION_LIMIT);
+        assert_eq!(factorize(360), vec![(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(
+            factorize(1_000_003 * 65_537),
+            vec![(65_537, 1), (1_000_003, 1)]
+        );
+        let after = small_primes_up_to(TRIAL_DIVISION_LIMIT);
+        assert!(Arc::ptr_eq(&before.primes, &after.primes));
+    }
+
+    #[test]
+    fn binomial_coefficients() {
+        use num_bigint::BigInt;
+
+        assert_eq!(binomial(10, 3), BigInt::from(120));
+        assert_eq!(binomial(5, 0), BigInt::from(1));
+        assert_eq!(binomial(5, 5), BigInt::from(1));
+        assert_eq!(binomial(3, 4), BigInt::from(0));
+        let c = binomial(100, 50);
+        assert_eq!(c.to_string(), "100891344545564193334812497256");
+        assert_eq!(c.to_string().len(), 30);
+    }
+
+    #[test]
+    fn factorial_values_and_digit_count() {
+        use num_bigint::BigInt;
+
+        assert_eq!(factorial(5), BigInt::from(120));
+        assert_eq!(factorial(20), BigInt::from(2_432_902_008_176_640_000u64));
+        assert_eq!(factorial(1000).to_string().len(), 2568);
+    }
+
+    #[test]
+    fn spigot_agrees_with_machin_at_100_digits() {
+        assert_eq!(pi_spigot(100), compute_pi(100));
+    }
+
+    #[test]
+    fn lcm_of_ranges() {
+        use num_bigint::BigInt;
+
+        assert_eq!(lcm_1_to_n(10), BigInt::from(2520));
+        assert_eq!(lcm_1_to_n(20), BigInt::from(232_792_560));
+    }
+
+    #[test]
+    fn threaded_sieve_count_matches_serial() {
+        for limit in [
+            0,
+            1,
+            2,
+            100,
+            SIEVE_SEGMENT - 1,
+            SIEVE_SEGMENT,
+            3 * SIEVE_SEGMENT + 17,
+        ] {
+            let serial = sieve_count(limit);
+            for threads in [1, 2, 3, 8] {
+                assert_eq!(
+                    sieve_count_segmented(limit, threads),
+                    serial,
+                    "limit {limit}, {threads} threads"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fermat_pseudoprimes_flag_341_not_340() {
+        assert_eq!(fermat_pseudoprimes(340, 341), vec![341]);
+        assert!(fermat_pseudoprimes(340, 340).is_empty());
+        assert_eq!(fermat_pseudoprimes(1, 1000), vec![341, 561, 645]);
+    }
+
+    #[test]
+    fn pi_find_locates_14_at_position_1() {
+        assert_eq!(pi_find("14", 100), Some(1));
+        assert_eq!(pi_find("999999", 100), None);
+    }
+
+    #[test]
+    fn farey_sequence_of_order_5() {
+        assert_eq!(
+            farey_sequence(5),
+            vec![
+                (0, 1),
+                (1, 5),
+                (1, 4),
+                (1, 3),
+                (2, 5),
+                (1, 2),
+                (3, 5),
+                (2, 3),
+                (3, 4),
+                (4, 5),
+                (1, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn first_palindromic_primes() {
+        assert_eq!(
+            palindromic_primes(10),
+            vec![2, 3, 5, 7, 11, 101, 131, 151, 181, 191]
+        );
+    }
+
+    #[test]
+    fn collatz_step_counts() {
+        assert_eq!(collatz_steps(1), 0);
+        assert_eq!(collatz_steps(27), 111);
+        // 3n + 1 overflows u64 on the first step, so the BigInt fallback
+        // has to give the same count as running in BigInt throughout.
+        let big = u64::MAX - 2;
+        assert_eq!(
+            collatz_steps(big),
+            collatz_steps_big(num_bigint::BigInt::from(big))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "collatz of zero")]
+    fn collatz_rejects_zero() {
+        collatz_steps(0);
+    }
+
+    #[test]
+    fn stern_brocot_paths() {
+        assert_eq!(stern_brocot_path(3, 5), "LRL");
+        assert_eq!(stern_brocot_path(2, 1), "R");
+        assert_eq!(stern_brocot_path(6, 10), "LRL");
+        assert_eq!(stern_brocot_path(1, 1), "");
+    }
+
+    #[test]
+    #[should_panic(expected = "Stern-Brocot fractions are positive")]
+    fn stern_brocot_rejects_zero_denominator() {
+        stern_brocot_path(3, 0);
+    }
+
+    #[test]
+    fn prime_count_at_one_million() {
+        assert_eq!(sieve_count(1_000_000), 78498);
+        let li = log_integral(1e6);
+        assert!((li - 78627.5).abs() < 1.0, "li(10^6) = {li}");
+    }
+
+    #[test]
+    fn partition_numbers() {
+        use num_bigint::BigInt;
+
+        assert_eq!(partition_count(5), BigInt::from(7));
+        assert_eq!(partition_count(10), BigInt::from(42));
+        assert_eq!(partition_count(100), BigInt::from(190_569_292));
+    }
+
+    #[test]
+    fn jacobi_known_values() {
+        assert_eq!(jacobi_symbol(1001, 9907), -1);
+        assert_eq!(jacobi_symbol(19, 45), 1);
+        assert_eq!(jacobi_symbol(8, 21), -1);
+        assert_eq!(jacobi_symbol(5, 21), 1);
+        assert_eq!(jacobi_symbol(-1, 7), -1);
+        assert_eq!(jacobi_symbol(30, 45), 0);
+        assert_eq!(jacobi_symbol(0, 1), 1);
+    }
+
+    #[test]
+    fn solovay_and_miller_rabin_agree_with_the_sieve() {
+        let limit = 10_000;
+        let mut prime = vec![false; limit + 1];
+        for p in sieve_primes(limit) {
+            prime[p as usize] = true;
+        }
+        for n in 1..=limit as u64 {
+            assert_eq!(is_prime(n), prime[n as usize], "miller-rabin on {n}");
+            assert_eq!(is_prime_solovay(n), prime[n as usize], "solovay on {n}");
+        }
+    }
+
+    #[test]
+    fn mertens_small_values() {
+        assert_eq!(mertens(1), 1);
+        assert_eq!(mertens(10), -1);
+        assert_eq!(mertens(100), 1);
+    }
+
+    #[test]
+    fn totient_sum_counts_farey_fractions() {
+        for n in 1..=30 {
+            assert_eq!(
+                totient_sum(n) as usize,
+                farey_sequence(n as u64).len() - 1,
+                "n = {n}"
+            );
+        }
+    }
+
+    #[test]
+    fn ulam_spiral_pixels() {
+        let side = 11;
+        let pbm = ulam_spiral_pbm(side);
+        let header = format!("P4\n{side} {side}\n");
+        assert!(pbm.starts_with(header.as_bytes()));
+        let rows: Vec<&[u8]> = pbm[header.len()..].chunks(side.div_ceil(8)).collect();
+        assert_eq!(rows.len(), side);
+        let pixel = |x: usize, y: usize| rows[y][x / 8] & (0x80 >> (x % 8)) != 0;
+        let black = (0..side)
+            .flat_map(|y| (0..side).map(move |x| (x, y)))
+            .filter(|&(x, y)| pixel(x, y))
+            .count();
+        assert_eq!(black, sieve_count(side * side));
+        // 1 sits at the centre and is not prime; 2 is to its right, 3 above.
+        assert!(!pixel(5, 5));
+        assert!(pixel(6, 5));
+        assert!(pixel(6, 4));
+    }
+
+    #[test]
+    fn small_repunit_primes() {
+        assert_eq!(repunit_primes(30), vec![2, 19, 23]);
+    }
+
+    #[test]
+    fn pascal_rows() {
+        use num_bigint::BigInt;
+
+        let row = |n: u64| -> Vec<String> { pascal_row(n).iter().map(|c| c.to_string()).collect() };
+        assert_eq!(row(0), ["1"]);
+        assert_eq!(row(4), ["1", "4", "6", "4", "1"]);
+        for n in [1u64, 7, 30, 100] {
+            let sum: BigInt = pascal_row(n).into_iter().sum();
+            assert_eq!(sum, BigInt::from(1u32) << n as usize, "row {n}");
+        }
+    }
+
+    #[test]
+    fn digit_sum_of_two_to_the_thousand() {
+        let n = parse_big_expression("2^1000").unwrap();
+        assert_eq!(digit_sum(&n), 1366);
+        assert_eq!(digital_root(&n), 7);
+        let f = parse_big_expression("10!").unwrap();
+        assert_eq!(digit_sum(&f), 27);
+        assert_eq!(digital_root(&f), 9);
+    }
+
+    #[test]
+    fn primitive_pythagorean_triples() {
+        let triples = primitive_triples(100);
+        for known in [(3, 4, 5), (5, 12, 13), (8, 15, 17)] {
+            assert!(triples.contains(&known), "{known:?} missing");
+        }
+        for &(a, b, c) in &triples {
+            assert_eq!(a * a + b * b, c * c);
+            assert_eq!(gcd_u64(gcd_u64(a, b), c), 1);
+            assert!(c <= 100);
+        }
+        assert_eq!(triples.len(), 16);
+    }
+
+    #[test]
+    fn first_euclid_mullin_terms() {
+        let terms: Vec<String> = euclid_mullin(9)
+            .iter()
+            .map(|(t, certain)| {
+                assert!(certain);
+                t.to_string()
+            })
+            .collect();
+        assert_eq!(
+            terms,
+            [
+                "2",
+                "3",
+                "7",
+                "43",
+                "13",
+                "53",
+                "5",
+                "6221671",
+                "38709183810571"
+            ]
+        );
+    }
+
+    #[test]
+    fn target_error_gives_enough_pi_digits() {
+        let digits = digits_for_error("1e-10").unwrap();
+        assert!(digits >= 10);
+        // Rounded to `digits` places, the result is within the requested
+        // error of pi; measure against 50 places as the reference.
+        let scaled = |text: &str| -> num_bigint::BigInt {
+            let places = text.len() - 2;
+            let integer: String = text.chars().filter(|c| *c != '.').collect();
+            integer.parse::<num_bigint::BigInt>().unwrap() * pow10(50 - places as u32)
+        };
+        let error = scaled(&compute_pi(digits)) - scaled(&compute_pi(50));
+        assert!(num_traits::Signed::abs(&error) * pow10(10) < pow10(50));
+        assert_eq!(digits_for_error("5e-8"), Some(8));
+        assert_eq!(digits_for_error("1e-100000"), Some(100_000));
+    }
+
+    #[test]
+    fn rational_decimal_marks_the_repetend() {
+        use num_bigint::BigInt;
+
+        let expand = |p: i32, q: i32| rational_decimal(BigInt::from(p), BigInt::from(q), 100);
+        assert_eq!(expand(1, 7), "0.(142857)");
+        assert_eq!(expand(22, 7), "3.(142857)");
+        assert_eq!(expand(1, 8), "0.125");
+    }
+
+    #[test]
+    fn e_continued_fraction_pattern() {
+        let terms: Vec<String> = e_continued_fraction(12)
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(
+            terms,
+            ["2", "1", "2", "1", "1", "4", "1", "1", "6", "1", "1", "8"]
+        );
+    }
+
+    #[test]
+    fn multiplicative_orders_mod_7() {
+        assert_eq!(multiplicative_order(2, 7), Some(3));
+        assert_eq!(multiplicative_order(3, 7), Some(6));
+        assert_eq!(multiplicative_order(1, 7), Some(1));
+        assert_eq!(multiplicative_order(7, 14), None);
+    }
+
+    #[test]
+    fn amicable_pairs_below_1000() {
+        assert_eq!(amicable_pairs(1000), vec![(220, 284)]);
+        assert_eq!(amicable_pairs(1300), vec![(220, 284), (1184, 1210)]);
+    }
+
+    #[test]
+    fn json_report_carries_numeric_digits_per_sec() {
+        let sampler = Sampler {
+            seconds: 0,
+            latency: false,
+            stream: false,
+            trace: false,
+        };
+        let result = bench_pi(&sampler, "pi", "machin", compute_pi, 20);
+        let json = render_results(&[result], OutputFormat::Json, false);
+        let report: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert!(report["results"][0]["digits_per_sec"].is_f64());
+    }
+
+    #[test]
+    fn thue_morse_first_16_terms() {
+        let terms: String = (0..16).map(|n| char::from(b'0' + thue_morse(n))).collect();
+        assert_eq!(terms, "0110100110010110");
+    }
+
+    #[test]
+    fn transposed_matmul_matches_naive() {
+        let (rows, inner, cols) = (5, 7, 6);
+        let a: Vec<f64> = (0..rows * inner).map(|i| (i as f64 * 0.37).sin()).collect();
+        let b: Vec<f64> = (0..inner * cols).map(|i| (i as f64 * 0.91).cos()).collect();
+        let bt = transpose(&b, inner, cols);
+        // Bit-identical from a cleared C with alpha = 1, as --repeatable runs.
+        let mut naive = vec![0.0; rows * cols];
+        let mut transposed = naive.clone();
+        matmul_rows(&a, &b, &mut naive, 0, inner, cols, 1.0);
+        matmul_rows_transposed(&a, &bt, &mut transposed, 0, inner, cols, 1.0);
+        assert_eq!(naive, transposed);
+        // Equal up to rounding on top of an existing C with alpha != 1.
+        let mut naive = vec![0.5; rows * cols];
+        let mut transposed = naive.clone();
+        matmul_rows(&a, &b, &mut naive, 0, inner, cols, 1.5);
+        matmul_rows_transposed(&a, &bt, &mut transposed, 0, inner, cols, 1.5);
+        for (x, y) in transposed.iter().zip(&naive) {
+            assert!((x - y).abs() <= 1e-12 * y.abs().max(1.0));
+        }
+    }
+
+    #[test]
+    fn champernowne_first_15_digits() {
+        assert_eq!(champernowne_digits(15, 10), "123456789101112");
+        assert_eq!(champernowne_digits(9, 2), "110111001");
+    }
+
+    #[test]
+    fn radicals() {
+        assert_eq!(radical(12), 6);
+        assert_eq!(radical(97), 97);
+        assert_eq!(radical(1), 1);
+    }
+
+    #[test]
+    fn abc_finds_1_plus_8_equals_9() {
+        let triples = abc_triples(100, 1.0);
+        let &(_, _, _, quality) = triples
+            .iter()
+            .find(|&&(a, b, c, _)| (a, b, c) == (1, 8, 9))
+            .expect("1 + 8 = 9 is an abc-triple");
+        assert!((quality - 1.226).abs() < 1e-3);
+    }
+
+    #[test]
+    fn kronecker_even_and_negative_n() {
+        assert_eq!(kronecker_symbol(3, 2), -1);
+        assert_eq!(kronecker_symbol(7, 2), 1);
+        assert_eq!(kronecker_symbol(4, 2), 0);
+        assert_eq!(kronecker_symbol(5, 6), 1);
+        assert_eq!(kronecker_symbol(-1, -1), -1);
+        assert_eq!(kronecker_symbol(3, -5), -1);
+        assert_eq!(kronecker_symbol(-3, -5), 1);
+        for a in -20..=20 {
+            for n in (1..40).step_by(2) {
+                assert_eq!(kronecker_symbol(a, n), jacobi_symbol(a, n as u64));
+            }
+        }
+    }
+
+    #[test]
+    fn legendre_symbols_mod_7() {
+        for a in [1, 2, 4] {
+            assert_eq!(legendre_symbol(a, 7), 1);
+        }
+        for a in [3, 5, 6] {
+            assert_eq!(legendre_symbol(a, 7), -1);
+        }
+        assert_eq!(legendre_symbol(14, 7), 0);
+        for p in [3, 5, 7, 11, 13, 97, 101] {
+            for a in -30..=30 {
+                assert_eq!(legendre_symbol(a, p), jacobi_symbol(a, p));
+            }
+        }
+    }
+
+    #[test]
+    fn tonelli_shanks_mod_13() {
+        assert!(matches!(tonelli_shanks(10, 13), Some(6 | 7)));
+        assert_eq!(tonelli_shanks(5, 13), None);
+        // 13 = 1 (mod 4) takes the general path, 11 = 3 (mod 4) the fast one.
+        let root = tonelli_shanks(5, 11).unwrap();
+        assert_eq!(root * root % 11, 5);
+    }
+
+    #[test]
+    fn convergents_of_truncated_pi() {
+        use num_bigint::BigInt;
+
+        let (num, den) = parse_rational("3.14159265358979").unwrap();
+        let fractions: Vec<(BigInt, BigInt)> = convergent_approximations(&num, &den, 6)
+            .into_iter()
+            .map(|approx| (approx.num, approx.den))
+            .collect();
+        assert!(fractions.contains(&(BigInt::from(22), BigInt::from(7))));
+        assert!(fractions.contains(&(BigInt::from(355), BigInt::from(113))));
+        for malformed in ["", "pi", "3.14.15", "22/", "1/0"] {
+            assert!(parse_rational(malformed).is_none(), "{malformed}");
+        }
+    }
+
+    #[test]
+    fn matmul_lane_kernels_agree() {
+        // 21 columns leave a tail after every block width.
+        let (rows, inner, cols) = (6, 9, 21);
+        let a: Vec<f64> = (0..rows * inner).map(|i| (i as f64 * 0.37).sin()).collect();
+        let b: Vec<f64> = (0..inner * cols).map(|i| (i as f64 * 0.91).cos()).collect();
+        let mut reference = vec![0.0; rows * cols];
+        matmul_rows(&a, &b, &mut reference, 0, inner, cols, 1.5);
+        for (lanes, _) in MATMUL_LANES {
+            let mut c = vec![0.0; rows * cols];
+            matmul_lanes_kernel(lanes)(&a, &b, &mut c, 0, inner, cols, 1.5);
+            for (x, y) in c.iter().zip(&reference) {
+                assert!((x - y).abs() <= 1e-12 * y.abs().max(1.0), "{lanes} lanes");
+            }
+        }
+    }
+
+    #[test]
+    fn highly_composite_below_100() {
+        assert_eq!(highly_composite(100), vec![1, 2, 4, 6, 12, 24, 36, 48, 60]);
+    }
+
+    #[test]
+    fn every_json_output_carries_schema_version() {
+        let sampler = Sampler {
+            seconds: 0,
+            latency: false,
+            stream: false,
+            trace: false,
+        };
+        let results = [bench_pi(&sampler, "pi", "machin", compute_pi, 20)];
+        let constant = ConstantReport {
+            schema_version: JSON_SCHEMA_VERSION,
+            constant: "pi".to_string(),
+            method: Some("machin".to_string()),
+            digits: 20,
+            value: compute_pi(20),
+        };
+        let history = HistoryEntry::new(&results[0], &[], 0);
+        let lines = [
+            String::from_utf8(render_results(&results, OutputFormat::Json, true)).unwrap(),
+            String::from_utf8(render_constant(&constant, OutputFormat::Json)).unwrap(),
+            serde_json::to_string(&history).unwrap(),
+            stream_sample_line("pi", "Computations/sec", 1.0, 2.0),
+        ];
+        for line in lines {
+            let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(json["schema_version"], JSON_SCHEMA_VERSION, "{line}");
+        }
+    }
+
+    #[test]
+    fn sums_of_two_squares() {
+        assert_eq!(as_sum_of_two_squares(5), Some((1, 2)));
+        assert!(matches!(as_sum_of_two_squares(25), Some((3, 4) | (0, 5))));
+        assert_eq!(as_sum_of_two_squares(3), None);
+    }
+
+    #[test]
+    fn pi_digits_take_bounded_work() {
+        let mut stream = pi_digits();
+        let digits: Vec<u8> = stream.by_ref().take(10).collect();
+        assert_eq!(digits, [3, 1, 4, 1, 5, 9, 2, 6, 5, 3]);
+        // About three series terms settle each digit.
+        assert!(stream.k < 50, "{} series terms for 10 digits", stream.k);
+    }
+
+    #[test]
+    fn carmichael_lambdas() {
+        assert_eq!(carmichael_lambda(8), 2);
+        assert_eq!(carmichael_lambda(15), 4);
+        for p in [2, 3, 7, 101, 65537] {
+            assert_eq!(carmichael_lambda(p), p - 1);
+        }
+    }
+
+    #[test]
+    fn random_prime_is_deterministic_for_a_seed() {
+        let (prime, _) = random_prime(16, &mut Rng::new(42));
+        assert_eq!(prime, num_bigint::BigInt::from(43591));
+        assert_eq!(prime.bits(), 16);
+        assert_eq!(random_prime(16, &mut Rng::new(42)).0, prime);
+        assert!(is_prime(43591));
+    }
+
+    #[test]
+    fn json_report_carries_numeric_digit_ops_per_sec() {
+        let sampler = Sampler {
+            seconds: 0,
+            latency: false,
+            stream: false,
+            trace: false,
+        };
+        let spec = OperandSpec {
+            digits: 64,
+            ..OperandSpec::default()
+        };
+        let result = bench_bigint(&sampler, &spec);
+        let json = render_results(&[result], OutputFormat::Json, false);
+        let report: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert!(report["results"][0]["digit_ops_per_sec"].is_f64());
+        assert!(report["results"][0].get("digits_per_sec").is_none());
+    }
+
+    #[test]
+    fn msgpack_report_round_trips() {
+        let sampler = Sampler {
+            seconds: 0,
+            latency: false,
+            stream: false,
+            trace: false,
+        };
+        let mut result = bench_pi(&sampler, "pi", "machin", compute_pi, 20);
+        (result.iterations, result.min, result.avg, result.max) = (7, 1.5, 2.25, 3.0);
+        result.latency = LatencyStats::from_nanos(&[120.0, 80.0, 95.0]);
+        result.throttle = Some(0.125);
+        let results = [result];
+        let report = BenchReport {
+            schema_version: JSON_SCHEMA_VERSION,
+            system: Some(SystemInfo::current()),
+            results: &results,
+        };
+        let bytes = render_results(&results, OutputFormat::MessagePack, true);
+        let decoded: serde_json::Value = msgpack::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, serde_json::to_value(&report).unwrap());
+
+        // The same bytes decode back into the structs themselves.
+        #[derive(serde::Deserialize)]
+        struct DecodedReport {
+            schema_version: u32,
+            system: Option<SystemInfo>,
+            results: Vec<BenchResult>,
+        }
+        let decoded: DecodedReport = msgpack::from_slice(&bytes).unwrap();
+        assert_eq!(decoded.schema_version, JSON_SCHEMA_VERSION);
+        assert!(decoded.system.is_some());
+        assert_eq!(decoded.results, results);
+        let single = msgpack::to_vec(&results[0]).unwrap();
+        assert_eq!(
+            msgpack::from_slice::<BenchResult>(&single).unwrap(),
+            results[0]
+        );
+
+        let constant = ConstantReport {
+            schema_version: JSON_SCHEMA_VERSION,
+            constant: "e".to_string(),
+            method: None,
+            digits: 30,
+            value: compute_e(30),
+        };
+        let bytes = render_constant(&constant, OutputFormat::MessagePack);
+        assert_eq!(
+            msgpack::from_slice::<ConstantReport>(&bytes).unwrap(),
+            constant
+        );
+    }
+
+    #[test]
+    fn wieferich_and_wilson_primes() {
+        assert_eq!(wieferich_primes(5000), vec![1093, 3511]);
+        assert_eq!(wilson_primes(1000), vec![5, 13, 563]);
+    }
+
+    #[test]
+    fn low_memory_paths_match_the_defaults() {
+        assert_eq!(
+            compute_pi_chudnovsky_lean(1000),
+            compute_pi_chudnovsky(1000)
+        );
+        assert_eq!(factorial_low_memory(1000), factorial(1000));
+    }
+
+    #[test]
+    fn modular_inverses() {
+        use num_bigint::BigInt;
+
+        assert_eq!(
+            mod_inverse(BigInt::from(3), BigInt::from(11)),
+            Some(BigInt::from(4))
+        );
+        assert_eq!(mod_inverse(BigInt::from(6), BigInt::from(9)), None);
+        let m: BigInt = BigInt::from(1) << 127;
+        let inverse: BigInt = "56713727820156410577229101238628035243".parse().unwrap();
+        assert_eq!(
+            mod_inverse(BigInt::from(3), m.clone()),
+            Some(inverse.clone())
+        );
+        assert_eq!(inverse * 3 % m, BigInt::from(1));
+    }
+
+    #[test]
+    fn thread_local_rows_match_shared_buffer() {
+        let (n, threads) = (37, 4);
+        let a: Vec<f64> = (0..n * n).map(|i| (i as f64 * 0.37).sin()).collect();
+        let b: Vec<f64> = (0..n * n).map(|i| (i as f64 * 0.91).cos()).collect();
+        let opts = MatmulOptions {
+            threads,
+            thread_local: true,
+            size: n,
+            gemm: Some((1.5, 0.5)),
+            ..MatmulOptions::default()
+        };
+        let work = |rows: &mut [f64], first_row: usize| {
+            prepare_c(rows, &opts);
+            matmul_rows(&a, &b, rows, first_row, n, n, 1.5);
+        };
+        let mut shared = vec![0.25; n * n];
+        for _ in 0..3 {
+            for_row_chunks(&mut shared, n, threads, work);
         }
+        let mut local = vec![0.25; n * n];
+        let firsts = with_thread_local_rows(&mut local, n, threads, work, |step| {
+            (0..3).map(|_| step()).collect::<Vec<f64>>()
+        });
+        assert_eq!(local, shared);
+        assert_eq!(firsts[2].to_bits(), shared[0].to_bits());
     }
 }
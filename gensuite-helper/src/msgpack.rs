@@ -0,0 +1,592 @@
+// A minimal MessagePack encoder and decoder for the serde derives behind
+// `--format json`. Structs are written as maps keyed by field name, so the
+// bytes decode to the same shape as the JSON; enums follow serde_json's
+// external tagging. Every value picks the smallest encoding that holds it.
+
+use serde::de::DeserializeOwned;
+use serde::ser::{self, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    value.serialize(Serializer { out: &mut out })?;
+    Ok(out)
+}
+
+// Decoding goes through serde_json::Value, which holds everything the
+// encoder writes; byte strings come back as arrays of numbers.
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let value = reader.value(0)?;
+    if reader.pos < bytes.len() {
+        return Err(Error(format!(
+            "{} bytes left after the MessagePack value",
+            bytes.len() - reader.pos
+        )));
+    }
+    serde_json::from_value(value).map_err(|err| Error(err.to_string()))
+}
+
+// Nesting limit, as in serde_json, so hostile input cannot overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], Error> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| Error("unexpected end of MessagePack data".to_string()))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn be<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.take(N)?.try_into().expect("take returns N bytes"))
+    }
+
+    fn len(&mut self, width: u8) -> Result<usize, Error> {
+        Ok(match width {
+            1 => self.be::<1>()?[0] as usize,
+            2 => u16::from_be_bytes(self.be()?) as usize,
+            _ => u32::from_be_bytes(self.be()?) as usize,
+        })
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error("MessagePack data is nested too deeply".to_string()));
+        }
+        let marker = self.be::<1>()?[0];
+        match marker {
+            0x00..=0x7f => Ok(Value::from(marker)),
+            0x80..=0x8f => self.map((marker & 0x0f) as usize, depth),
+            0x90..=0x9f => self.array((marker & 0x0f) as usize, depth),
+            0xa0..=0xbf => self.str((marker & 0x1f) as usize),
+            0xc0 => Ok(Value::Null),
+            0xc2 => Ok(Value::Bool(false)),
+            0xc3 => Ok(Value::Bool(true)),
+            0xc4..=0xc6 => {
+                let len = self.len(1 << (marker - 0xc4))?;
+                Ok(Value::from(self.take(len)?.to_vec()))
+            }
+            // serde_json turns NaN and infinities into null, as to_value does.
+            0xca => Ok(Value::from(f32::from_be_bytes(self.be()?) as f64)),
+            0xcb => Ok(Value::from(f64::from_be_bytes(self.be()?))),
+            0xcc => Ok(Value::from(self.be::<1>()?[0])),
+            0xcd => Ok(Value::from(u16::from_be_bytes(self.be()?))),
+            0xce => Ok(Value::from(u32::from_be_bytes(self.be()?))),
+            0xcf => Ok(Value::from(u64::from_be_bytes(self.be()?))),
+            0xd0 => Ok(Value::from(i8::from_be_bytes(self.be()?))),
+            0xd1 => Ok(Value::from(i16::from_be_bytes(self.be()?))),
+            0xd2 => Ok(Value::from(i32::from_be_bytes(self.be()?))),
+            0xd3 => Ok(Value::from(i64::from_be_bytes(self.be()?))),
+            0xd9..=0xdb => {
+                let len = self.len(1 << (marker - 0xd9))?;
+                self.str(len)
+            }
+            0xdc | 0xdd => {
+                let len = self.len(2 << (marker - 0xdc))?;
+                self.array(len, depth)
+            }
+            0xde | 0xdf => {
+                let len = self.len(2 << (marker - 0xde))?;
+                self.map(len, depth)
+            }
+            0xe0..=0xff => Ok(Value::from(marker as i8)),
+            // 0xc1 is never used; the rest are ext types, which the encoder
+            // does not write.
+            _ => Err(Error(format!(
+                "unsupported MessagePack marker 0x{marker:02x}"
+            ))),
+        }
+    }
+
+    fn str(&mut self, len: usize) -> Result<Value, Error> {
+        String::from_utf8(self.take(len)?.to_vec())
+            .map(Value::String)
+            .map_err(|_| Error("MessagePack string is not UTF-8".to_string()))
+    }
+
+    fn array(&mut self, len: usize, depth: usize) -> Result<Value, Error> {
+        (0..len)
+            .map(|_| self.value(depth + 1))
+            .collect::<Result<_, _>>()
+            .map(Value::Array)
+    }
+
+    fn map(&mut self, len: usize, depth: usize) -> Result<Value, Error> {
+        let mut map = serde_json::Map::new();
+        for _ in 0..len {
+            let Value::String(key) = self.value(depth + 1)? else {
+                return Err(Error("MessagePack map keys must be strings".to_string()));
+            };
+            let value = self.value(depth + 1)?;
+            map.insert(key, value);
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+struct Serializer<'a> {
+    out: &'a mut Vec<u8>,
+}
+
+fn write_uint(out: &mut Vec<u8>, v: u64) {
+    match v {
+        0..=0x7f => out.push(v as u8),
+        0x80..=0xff => out.extend([0xcc, v as u8]),
+        0x100..=0xffff => {
+            out.push(0xcd);
+            out.extend((v as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(0xce);
+            out.extend((v as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(0xcf);
+            out.extend(v.to_be_bytes());
+        }
+    }
+}
+
+fn write_int(out: &mut Vec<u8>, v: i64) {
+    if v >= 0 {
+        return write_uint(out, v as u64);
+    }
+    if v >= -32 {
+        out.push(v as u8);
+    } else if v >= i8::MIN as i64 {
+        out.extend([0xd0, v as u8]);
+    } else if v >= i16::MIN as i64 {
+        out.push(0xd1);
+        out.extend((v as i16).to_be_bytes());
+    } else if v >= i32::MIN as i64 {
+        out.push(0xd2);
+        out.extend((v as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend(v.to_be_bytes());
+    }
+}
+
+// The length header shared by str, bin, array and map: the fix form's
+// marker (if the type has one) and limit, then the 8/16/32-bit markers.
+fn write_len(
+    out: &mut Vec<u8>,
+    len: usize,
+    fix: Option<(u8, usize)>,
+    markers: [Option<u8>; 3],
+) -> Result<(), Error> {
+    match (fix, markers) {
+        (Some((base, limit)), _) if len < limit => out.push(base | len as u8),
+        (_, [Some(m8), _, _]) if len <= u8::MAX as usize => out.extend([m8, len as u8]),
+        (_, [_, Some(m16), _]) if len <= u16::MAX as usize => {
+            out.push(m16);
+            out.extend((len as u16).to_be_bytes());
+        }
+        (_, [_, _, Some(m32)]) if len <= u32::MAX as usize => {
+            out.push(m32);
+            out.extend((len as u32).to_be_bytes());
+        }
+        _ => return Err(Error(format!("length {len} is too large for MessagePack"))),
+    }
+    Ok(())
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) -> Result<(), Error> {
+    write_len(
+        out,
+        s.len(),
+        Some((0xa0, 32)),
+        [Some(0xd9), Some(0xda), Some(0xdb)],
+    )?;
+    out.extend(s.as_bytes());
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum Container {
+    Array,
+    Map,
+}
+
+fn write_container(out: &mut Vec<u8>, kind: Container, len: usize) -> Result<(), Error> {
+    match kind {
+        Container::Array => write_len(out, len, Some((0x90, 16)), [None, Some(0xdc), Some(0xdd)]),
+        Container::Map => write_len(out, len, Some((0x80, 16)), [None, Some(0xde), Some(0xdf)]),
+    }
+}
+
+// Elements are buffered so the header can carry the exact count, even for
+// sequences of unknown length and structs with skipped fields.
+struct Compound<'a> {
+    out: &'a mut Vec<u8>,
+    kind: Container,
+    buf: Vec<u8>,
+    count: usize,
+}
+
+impl<'a> Compound<'a> {
+    fn new(out: &'a mut Vec<u8>, kind: Container) -> Self {
+        Compound {
+            out,
+            kind,
+            buf: Vec::new(),
+            count: 0,
+        }
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(Serializer { out: &mut self.buf })
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        write_str(&mut self.buf, key)?;
+        self.count += 1;
+        self.element(value)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        write_container(self.out, self.kind, self.count)?;
+        self.out.extend(self.buf);
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.out.push(if v { 0xc3 } else { 0xc2 });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        write_int(self.out, v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        write_uint(self.out, v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.out.push(0xca);
+        self.out.extend(v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.out.push(0xcb);
+        self.out.extend(v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        write_str(self.out, v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        write_str(self.out, v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        write_len(
+            self.out,
+            v.len(),
+            None,
+            [Some(0xc4), Some(0xc5), Some(0xc6)],
+        )?;
+        self.out.extend(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.out.push(0xc0);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        write_str(self.out, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let mut map = Compound::new(self.out, Container::Map);
+        map.field(variant, value)?;
+        map.finish()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self.out, Container::Array))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self.out, Container::Array))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self.out, Container::Array))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        // The one-entry {variant: [...]} wrapper is written up front.
+        self.out.push(0x81);
+        write_str(self.out, variant)?;
+        Ok(Compound::new(self.out, Container::Array))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self.out, Container::Map))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>, Error> {
+        Ok(Compound::new(self.out, Container::Map))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.out.push(0x81);
+        write_str(self.out, variant)?;
+        Ok(Compound::new(self.out, Container::Map))
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.count += 1;
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.count += 1;
+        self.element(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_width_round_trips() {
+        let long = "x".repeat(300);
+        let values = [
+            serde_json::json!([0, 127, 128, 255, 256, 65535, 65536, u32::MAX, u64::MAX]),
+            serde_json::json!([-1, -32, -33, -128, -129, -32768, -32769, i64::MIN]),
+            serde_json::json!(["", "a".repeat(31), "a".repeat(32), long, null, true, false]),
+            serde_json::json!({"pi": 3.25, "nested": {"list": (0..20).collect::<Vec<_>>()}}),
+        ];
+        for value in values {
+            let bytes = to_vec(&value).unwrap();
+            assert_eq!(from_slice::<Value>(&bytes).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_and_trailing_bytes() {
+        let bytes = to_vec(&serde_json::json!({"suite": "pi"})).unwrap();
+        assert!(from_slice::<Value>(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0xc0);
+        assert!(from_slice::<Value>(&trailing).is_err());
+        assert!(from_slice::<Value>(&[0xc1]).is_err());
+    }
+}
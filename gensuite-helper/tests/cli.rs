@@ -0,0 +1,118 @@
+use std::process::{Command, Output};
+
+fn helper(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gensuite-helper"))
+        .args(args)
+        .output()
+        .expect("helper runs")
+}
+
+fn exit_code(args: &[&str]) -> Option<i32> {
+    helper(args).status.code()
+}
+
+// A file unique to this test process, removed by the caller.
+fn temp_file(name: &str, contents: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("gensuite-cli-{}-{name}", std::process::id()));
+    std::fs::write(&path, contents).expect("temp file written");
+    path
+}
+
+#[test]
+fn success_exits_0() {
+    let out = helper(&["radical", "12"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&out.stdout).trim(), "6");
+}
+
+#[test]
+fn unknown_command_exits_2() {
+    assert_eq!(exit_code(&["no-such-command"]), Some(2));
+}
+
+#[test]
+fn bad_numeric_argument_exits_2() {
+    assert_eq!(exit_code(&["radical", "twelve"]), Some(2));
+    assert_eq!(exit_code(&["carmichael", "0"]), Some(2));
+}
+
+#[test]
+fn failing_verify_exits_1() {
+    let path = temp_file("not-prime.txt", "2 3 5 9 11\n");
+    let code = exit_code(&["verify-primes", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).ok();
+    assert_eq!(code, Some(1));
+}
+
+#[test]
+fn passing_verify_exits_0() {
+    let path = temp_file("primes.txt", "2, 3, 5, 7, 11\n");
+    let code = exit_code(&["verify-primes", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).ok();
+    assert_eq!(code, Some(0));
+}
+
+#[test]
+fn missing_input_file_exits_1() {
+    assert_eq!(
+        exit_code(&["verify-primes", "/nonexistent/gensuite/primes.txt"]),
+        Some(1)
+    );
+}
+
+#[test]
+fn low_memory_flag_before_the_digit_count() {
+    let out = helper(&["pi", "--low-memory", "30", "--method", "chudnovsky"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout).trim(),
+        "3.141592653589793238462643383280"
+    );
+}
+
+#[test]
+fn malformed_operand_addend_exits_2() {
+    assert_eq!(
+        exit_code(&["bench-bigint", "0", "--operand-addend", "1,x,3"]),
+        Some(2)
+    );
+}
+
+#[test]
+fn malformed_max_den_exits_2() {
+    assert_eq!(exit_code(&["pi-rational", "--max-den", "abc"]), Some(2));
+}
+
+#[test]
+fn malformed_precompute_primes_exits_2() {
+    assert_eq!(
+        exit_code(&["--precompute-primes", "lots", "radical", "12"]),
+        Some(2)
+    );
+}
+
+#[test]
+fn oversized_pseudoprime_range_exits_2() {
+    assert_eq!(
+        exit_code(&["pseudoprimes", "1", "18446744073709551615"]),
+        Some(2)
+    );
+}
+
+#[test]
+fn bench_all_counts_every_suite_against_max_duration() {
+    // 4 suites x 1000s is over the 3600s default, though one suite is not.
+    assert_eq!(exit_code(&["bench-all", "1000"]), Some(2));
+    assert_eq!(exit_code(&["bench-sieve", "2000", "--cold"]), Some(2));
+}
+
+#[test]
+fn malformed_pi_options_exit_2() {
+    assert_eq!(exit_code(&["pi", "--compare-methods", "abc"]), Some(2));
+    assert_eq!(exit_code(&["pi", "--budget", "soon"]), Some(2));
+}
+
+#[test]
+fn malformed_max_cv_exits_2() {
+    assert_eq!(exit_code(&["bench-div", "0", "--max-cv", "5%"]), Some(2));
+}